@@ -0,0 +1,8 @@
+#![no_main]
+
+use ckb_typed_message_signing::fuzz::fuzz_parse_witness;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    fuzz_parse_witness(data);
+});
@@ -0,0 +1,176 @@
+//! Renders scripts carried in typed messages as human readable CKB
+//! addresses, so wallets can show the destinations a message refers to.
+//! Only the full address format defined in CKB RFC 0021 is generated.
+
+use crate::{
+    schemas::basic::{TypedMessageReader, TypedMessageUnionReader, ValueReader, ValueUnionReader},
+    Error,
+};
+use ckb_std::ckb_types::packed::ScriptReader;
+use molecule::prelude::Reader;
+use std::{string::String, vec::Vec};
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32M_CONST: u32 = 0x2bc830a3;
+const FULL_FORMAT: u8 = 0x00;
+
+/// Decodes the value at `field_index` of the top-level message struct as a
+/// CKB address. The value must be a Bytes holding a molecule serialized
+/// Script. Note that a script hash cannot be rendered as an address, since
+/// an address encodes the full script.
+pub fn message_field_as_ckb_address(
+    typed_message: &TypedMessageReader,
+    field_index: usize,
+    is_mainnet: bool,
+) -> Result<String, Error> {
+    let TypedMessageUnionReader::EIP712(eip712) = typed_message.to_enum();
+    let serialized_value = eip712
+        .message()
+        .values()
+        .get(field_index)
+        .ok_or(Error::InvalidAddressField)?;
+    let value = ValueReader::from_slice(serialized_value.raw_data())?;
+    let script = match value.to_enum() {
+        ValueUnionReader::Bytes(b) => ScriptReader::from_slice(b.raw_data())?,
+        _ => return Err(Error::InvalidAddressField),
+    };
+
+    let mut payload = Vec::with_capacity(script.as_slice().len());
+    payload.push(FULL_FORMAT);
+    payload.extend_from_slice(script.code_hash().raw_data());
+    payload.extend_from_slice(script.hash_type().as_slice());
+    payload.extend_from_slice(script.args().raw_data());
+    let hrp = if is_mainnet { "ckb" } else { "ckt" };
+    Ok(bech32m_encode(hrp, &payload))
+}
+
+fn bech32m_encode(hrp: &str, payload: &[u8]) -> String {
+    let mut data = Vec::with_capacity(payload.len() * 8 / 5 + 7);
+    let mut bits = 0u32;
+    let mut available = 0;
+    for b in payload {
+        bits = (bits << 8) | (*b as u32);
+        available += 8;
+        while available >= 5 {
+            available -= 5;
+            data.push(((bits >> available) & 0x1F) as u8);
+        }
+    }
+    if available > 0 {
+        data.push(((bits << (5 - available)) & 0x1F) as u8);
+    }
+
+    let mut values: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|c| c & 0x1F));
+    values.extend_from_slice(&data);
+    values.extend_from_slice(&[0; 6]);
+    let checksum = polymod(&values) ^ BECH32M_CONST;
+    data.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 0x1F) as u8));
+
+    let mut result = String::from(hrp);
+    result.push('1');
+    result.extend(data.iter().map(|d| CHARSET[*d as usize] as char));
+    result
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk = 1u32;
+    for v in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ (*v as u32);
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::basic::{
+        Byte32, Bytes, Hash, SerializedValues, Struct, TypedMessage, Value, EIP712,
+    };
+    use ckb_std::ckb_types::{
+        bytes,
+        core::ScriptHashType,
+        packed::{self, Script},
+        prelude::*,
+    };
+    use molecule::prelude::{Builder, Byte, Entity};
+    use std::vec;
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn message(values: Vec<Value>) -> TypedMessage {
+        let hash = Hash::new_builder()
+            .set(Byte32::new_unchecked(vec![0; 32].into()))
+            .build();
+        let values = values
+            .iter()
+            .map(|v| {
+                Bytes::new_builder()
+                    .set(v.as_slice().iter().map(|b| Byte::new(*b)).collect())
+                    .build()
+            })
+            .collect();
+        let message = Struct::new_builder()
+            .type_hash(hash.clone())
+            .values(SerializedValues::new_builder().set(values).build())
+            .build();
+        TypedMessage::new_builder()
+            .set(
+                EIP712::new_builder()
+                    .domain_separator(hash)
+                    .message(message)
+                    .build(),
+            )
+            .build()
+    }
+
+    // Full address example from CKB RFC 0021
+    #[test]
+    fn test_full_address() {
+        let script = Script::new_builder()
+            .code_hash(packed::Byte32::new_unchecked(
+                from_hex("9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8").into(),
+            ))
+            .hash_type(ScriptHashType::Type.into())
+            .args(bytes::Bytes::from(from_hex("b39bbc0b3673c7d36450bc14cfcdad2d559c6c64")).pack())
+            .build();
+        let bytes_value = Value::new_builder()
+            .set(
+                Bytes::new_builder()
+                    .set(script.as_slice().iter().map(|b| Byte::new(*b)).collect())
+                    .build(),
+            )
+            .build();
+        let message = message(vec![Value::default(), bytes_value]);
+
+        assert_eq!(
+            message_field_as_ckb_address(&message.as_reader(), 1, true).as_deref(),
+            Ok("ckb1qzda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xwsqdnnw7qkdnnclfkg59uzn8umtfd2kwxceqxwquc4")
+        );
+        assert_eq!(
+            message_field_as_ckb_address(&message.as_reader(), 1, false).as_deref(),
+            Ok("ckt1qzda0cr08m85hc8jlnfp3zer7xulejywt49kt2rr0vthywaa50xwsqdnnw7qkdnnclfkg59uzn8umtfd2kwxceqgutnjd")
+        );
+        assert_eq!(
+            message_field_as_ckb_address(&message.as_reader(), 0, true),
+            Err(Error::InvalidAddressField)
+        );
+        assert_eq!(
+            message_field_as_ckb_address(&message.as_reader(), 2, true),
+            Err(Error::InvalidAddressField)
+        );
+    }
+}
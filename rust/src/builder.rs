@@ -0,0 +1,134 @@
+//! Signing side helpers assembling ExtendedWitness structures, mirroring
+//! `serializeSighashWithActionWitness` in the TypeScript library. The
+//! generated witness can be decoded back by `fetch_sighash_with_action`.
+
+use crate::schemas::{
+    basic::{Bytes, Sighash, SighashWithAction, TypedMessage},
+    top_level::ExtendedWitness,
+};
+use molecule::prelude::{Builder, Byte, Entity};
+use std::vec::Vec;
+
+/// Assembles a SighashWithAction step by step, for wallets filling in the
+/// seal only after the message is signed. Unset fields are left empty.
+#[derive(Debug, Default, Clone)]
+pub struct SighashWithActionBuilder {
+    message: TypedMessage,
+    seal: Vec<u8>,
+}
+
+impl SighashWithActionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_message(mut self, message: TypedMessage) -> Self {
+        self.message = message;
+        self
+    }
+
+    /// Sets the lock field, usually holding the signature
+    pub fn set_seal(mut self, seal: &[u8]) -> Self {
+        self.seal = seal.to_vec();
+        self
+    }
+
+    pub fn build(self) -> SighashWithAction {
+        SighashWithAction::new_builder()
+            .lock(pack_bytes(&self.seal))
+            .message(self.message)
+            .build()
+    }
+
+    /// Builds the SighashWithAction wrapped in an ExtendedWitness
+    pub fn build_witness(self) -> ExtendedWitness {
+        ExtendedWitness::new_builder().set(self.build()).build()
+    }
+}
+
+/// Builds a SighashWithAction witness from the typed message, and a lock
+/// field usually holding the signature.
+pub fn build_sighash_with_action_witness(message: TypedMessage, lock: &[u8]) -> ExtendedWitness {
+    SighashWithActionBuilder::new()
+        .set_message(message)
+        .set_seal(lock)
+        .build_witness()
+}
+
+/// Builds a plain Sighash witness from the lock field.
+pub fn build_sighash_witness(lock: &[u8]) -> ExtendedWitness {
+    let sighash = Sighash::new_builder().lock(pack_bytes(lock)).build();
+    ExtendedWitness::new_builder().set(sighash).build()
+}
+
+fn pack_bytes(data: &[u8]) -> Bytes {
+    Bytes::new_builder()
+        .set(data.iter().map(|b| Byte::new(*b)).collect::<Vec<_>>())
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fetch_sighash_with_action, parse_extended_witness,
+        schemas::{
+            basic::{Byte32, Hash, Struct, EIP712},
+            top_level::ExtendedWitnessUnionReader,
+        },
+        MockTransaction,
+    };
+    use molecule::prelude::Reader;
+    use std::vec;
+
+    fn message() -> TypedMessage {
+        let hash = |n: u8| {
+            Hash::new_builder()
+                .set(Byte32::new_unchecked(vec![n; 32].into()))
+                .build()
+        };
+        let eip712 = EIP712::new_builder()
+            .domain_separator(hash(1))
+            .message(Struct::new_builder().type_hash(hash(2)).build())
+            .build();
+        TypedMessage::new_builder().set(eip712).build()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let witness = SighashWithActionBuilder::new()
+            .set_message(message())
+            .set_seal(&[5; 65])
+            .build_witness();
+        let witness = witness.as_slice().to_vec();
+        assert_eq!(
+            build_sighash_with_action_witness(message(), &[5; 65]).as_slice(),
+            witness
+        );
+
+        match parse_extended_witness(&witness) {
+            Ok(ExtendedWitnessUnionReader::SighashWithAction(s)) => {
+                assert_eq!(s.lock().raw_data(), [5; 65]);
+                assert_eq!(s.message().as_slice(), message().as_slice());
+            }
+            _ => panic!("not a SighashWithAction witness"),
+        }
+
+        MockTransaction {
+            input_count: 1,
+            group_inputs: vec![0],
+            witnesses: vec![witness],
+            ..Default::default()
+        }
+        .install();
+        assert_eq!(
+            fetch_sighash_with_action().map(|s| s.as_slice().to_vec()),
+            Ok(SighashWithActionBuilder::new()
+                .set_message(message())
+                .set_seal(&[5; 65])
+                .build()
+                .as_slice()
+                .to_vec())
+        );
+    }
+}
@@ -3,27 +3,181 @@
 //! spec. It is not directly used now(sighash-all message is generated now for
 //! ExtendedWitness), but mostly put here for a reference and future-proof reason.
 
+#[cfg(feature = "std")]
+use crate::schemas::basic::SighashWithAction;
 use crate::schemas::basic::{
-    HashReader, HashUnionReader, StructReader, TypedMessage, TypedMessageReader,
-    TypedMessageUnionReader, ValueReader, ValueUnionReader,
+    BoolReader, EIP712DomainReader, EIP712Reader, HashReader, HashUnionReader, RefBytesReader,
+    SerializedValuesReader, StructReader, TypedMessage, TypedMessageReader,
+    TypedMessageUnionReader, Uint32Reader, ValueReader, ValueUnionReader,
 };
-use ckb_std::{
-    ckb_constants::Source,
-    error::SysError,
-    syscalls::{load_cell_data, load_transaction},
+use crate::{
+    sys_error_key,
+    syscalls::{load_cell_data, load_script_hash, load_transaction},
 };
+use alloc::vec::Vec;
+use ckb_std::{ckb_constants::Source, error::SysError};
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+#[cfg(feature = "std")]
+use molecule::prelude::Entity;
 use molecule::{error::VerificationError, prelude::Reader};
-use sha3::{Digest, Keccak256};
+use sha3::{
+    digest::{consts::U32, OutputSizeUser},
+    Digest, Keccak256,
+};
+#[cfg(feature = "std")]
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+};
+
+/// Location of a value in typed message. Structs & arrays are numbered by
+/// `struct_index` in the order they are visited, depth first, starting from
+/// 0 for the top-level message struct. `value_index` is the index of the
+/// value within its struct or array.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, PartialOrd, Ord, Hash, Default)]
+pub struct FieldPath {
+    pub struct_index: usize,
+    pub value_index: usize,
+}
 
 #[derive(Eq, PartialEq, Debug, Clone, Copy)]
 pub enum Error {
     MoleculeEncoding,
     Sys(SysError),
     CellDataEof,
-    InvalidSource,
-    InvalidBool,
-    InvalidNumber,
-    InvalidFixedBytes,
+    InvalidSource(u64),
+    InvalidBool(FieldPath),
+    InvalidNumber(FieldPath),
+    InvalidFixedBytes(FieldPath),
+    InvalidRefLength,
+    OutputRefCell,
+    ValueTooDeep,
+    InvalidUtf8,
+    InvalidRefFlag,
+    NumberTooLarge,
+    /// Index of the struct, see `FieldPath`, holding a different number of
+    /// values than expected
+    FieldCountMismatch(usize),
+    /// RefCell or RefBytes referencing a cell beyond its source
+    RefCellIndexOutOfBound,
+    /// RefTransaction starting at or beyond the end of the transaction
+    RefTransactionOutOfBound,
+    /// Value of a different variant than the one asked for, e.g. Bytes
+    /// passed to `value_as_u128`
+    UnexpectedValueType,
+}
+
+impl Error {
+    /// Short static description of the error, usable in no_std scripts
+    /// without allocation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Error::MoleculeEncoding => "molecule encoding",
+            Error::Sys(_) => "syscall error",
+            Error::CellDataEof => "cell data eof",
+            Error::InvalidSource(_) => "invalid source",
+            Error::InvalidBool(_) => "invalid bool",
+            Error::InvalidNumber(_) => "invalid number",
+            Error::InvalidFixedBytes(_) => "invalid fixed bytes",
+            Error::InvalidRefLength => "invalid ref length",
+            Error::OutputRefCell => "output ref cell",
+            Error::ValueTooDeep => "value too deep",
+            Error::InvalidUtf8 => "invalid utf8",
+            Error::InvalidRefFlag => "invalid ref flag",
+            Error::NumberTooLarge => "number too large",
+            Error::FieldCountMismatch(_) => "field count mismatch",
+            Error::RefCellIndexOutOfBound => "ref cell index out of bound",
+            Error::RefTransactionOutOfBound => "ref transaction out of bound",
+            Error::UnexpectedValueType => "unexpected value type",
+        }
+    }
+
+    /// Stable exit code of the error. Syscall errors keep the numbers used
+    /// by CKB: 1 for IndexOutOfBound, 2 for ItemMissing, 3 for
+    /// LengthNotEnough and 4 for Encoding, other syscall errors all map to 9.
+    /// The remaining variants take codes starting from 40, in the order they
+    /// are declared, so they never collide with codes of `crate::Error`.
+    /// Codes of existing variants never change.
+    pub fn code(&self) -> i8 {
+        match self {
+            Error::Sys(SysError::IndexOutOfBound) => 1,
+            Error::Sys(SysError::ItemMissing) => 2,
+            Error::Sys(SysError::LengthNotEnough(_)) => 3,
+            Error::Sys(SysError::Encoding) => 4,
+            Error::Sys(_) => 9,
+            Error::MoleculeEncoding => 40,
+            Error::CellDataEof => 41,
+            Error::InvalidSource(_) => 42,
+            Error::InvalidBool(_) => 43,
+            Error::InvalidNumber(_) => 44,
+            Error::InvalidFixedBytes(_) => 45,
+            Error::InvalidRefLength => 46,
+            Error::OutputRefCell => 47,
+            Error::ValueTooDeep => 48,
+            Error::InvalidUtf8 => 49,
+            Error::InvalidRefFlag => 50,
+            Error::NumberTooLarge => 51,
+            Error::FieldCountMismatch(_) => 52,
+            Error::RefCellIndexOutOfBound => 53,
+            Error::RefTransactionOutOfBound => 54,
+            Error::UnexpectedValueType => 55,
+        }
+    }
+
+    // Key for ordering and hashing: variant in declaration order, followed
+    // by the inner value, see `sys_error_key` for syscall errors.
+    pub(crate) fn sort_key(&self) -> (u8, u8, u64, u64) {
+        let path = |p: &FieldPath| (p.struct_index as u64, p.value_index as u64);
+        match self {
+            Error::MoleculeEncoding => (0, 0, 0, 0),
+            Error::Sys(e) => {
+                let (k, v) = sys_error_key(e);
+                (1, k, v, 0)
+            }
+            Error::CellDataEof => (2, 0, 0, 0),
+            Error::InvalidSource(s) => (3, 0, *s, 0),
+            Error::InvalidBool(p) => (4, 0, path(p).0, path(p).1),
+            Error::InvalidNumber(p) => (5, 0, path(p).0, path(p).1),
+            Error::InvalidFixedBytes(p) => (6, 0, path(p).0, path(p).1),
+            Error::InvalidRefLength => (7, 0, 0, 0),
+            Error::OutputRefCell => (8, 0, 0, 0),
+            Error::ValueTooDeep => (9, 0, 0, 0),
+            Error::InvalidUtf8 => (10, 0, 0, 0),
+            Error::InvalidRefFlag => (11, 0, 0, 0),
+            Error::NumberTooLarge => (12, 0, 0, 0),
+            Error::FieldCountMismatch(i) => (13, 0, *i as u64, 0),
+            Error::RefCellIndexOutOfBound => (14, 0, 0, 0),
+            Error::RefTransactionOutOfBound => (15, 0, 0, 0),
+            Error::UnexpectedValueType => (16, 0, 0, 0),
+        }
+    }
+}
+
+impl PartialOrd for Error {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Error {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl Hash for Error {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sort_key().hash(state);
+    }
+}
+
+impl From<Error> for i8 {
+    fn from(e: Error) -> Self {
+        e.code()
+    }
 }
 
 impl From<SysError> for Error {
@@ -49,22 +203,469 @@ impl TryFrom<&TypedMessage> for Eip712Hash {
     }
 }
 
+#[cfg(feature = "std")]
+impl Eip712Hash {
+    /// 0x prefixed lowercase hex, matching how wallets display hashes
+    pub fn to_hex(&self) -> String {
+        std::format!("{}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for Eip712Hash {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "0x")?;
+        for b in &self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Test utility checking that an off-chain signed TypedMessage is the one
+/// carried in a SighashWithAction witness.
+///
+/// The two hashing schemes deliberately commit to different preimages:
+/// sighash-all hashes the molecule serialized message bytes together with
+/// tx hash and extra witnesses using blake2b, while EIP-712 hashes the
+/// decoded message structure using keccak256. The resulting hashes are never
+/// equal, so this instead asserts that both schemes consume exactly the same
+/// message bytes, and that EIP-712 hash can be built from the message. Since
+/// there are no syscalls off-chain, only Byte32 hashes are supported here.
+#[cfg(feature = "std")]
+pub fn assert_hashes_consistent(typed_message: &TypedMessage, sighash: &SighashWithAction) {
+    assert_eq!(
+        typed_message.as_slice(),
+        sighash.message().as_slice(),
+        "SighashWithAction carries a different message"
+    );
+    if let Err(e) = Eip712Hash::try_from(typed_message) {
+        panic!("Building EIP-712 hash fails: {:?}", e);
+    }
+}
+
 pub fn build_typed_message_hash<'r>(
     typed_message: &TypedMessageReader<'r>,
 ) -> Result<[u8; 32], Error> {
-    let eip712 = match typed_message.to_enum() {
-        TypedMessageUnionReader::EIP712(eip712) => eip712,
+    build_typed_message_hash_with::<Keccak256>(typed_message)
+}
+
+/// Same as `build_typed_message_hash`, but all hashing, including hashing of
+/// structs, bytes & strings, is done with digest `D` instead of keccak256.
+/// `D` must produce 32-byte outputs, which is enforced at compile time.
+pub fn build_typed_message_hash_with<'r, D>(
+    typed_message: &TypedMessageReader<'r>,
+) -> Result<[u8; 32], Error>
+where
+    D: Digest + Default + OutputSizeUser<OutputSize = U32>,
+{
+    build_typed_message_hash_with_prefix::<D>(typed_message, EIP712_PREFIX)
+}
+
+/// Prefix prepended to domain separator & message hash by EIP-712
+pub const EIP712_PREFIX: &[u8] = b"\x19\x01";
+
+/// Same as `build_typed_message_hash_with`, but `prefix` is used in place of
+/// `EIP712_PREFIX` when hashing the domain separator & message hash
+/// together, so the same structured encoding can serve schemes other than
+/// EIP-712.
+pub fn build_typed_message_hash_with_prefix<'r, D>(
+    typed_message: &TypedMessageReader<'r>,
+    prefix: &[u8],
+) -> Result<[u8; 32], Error>
+where
+    D: Digest + Default + OutputSizeUser<OutputSize = U32>,
+{
+    build_typed_message_hash_inner::<D>(typed_message, prefix, HashOptions::default())
+}
+
+/// Same as `build_typed_message_hash`, but Int & Uint values must use
+/// minimal encodings: unsigned values cannot have leading zero bytes, signed
+/// values cannot have redundant sign bytes. Otherwise `InvalidNumber` is
+/// returned, so signers and verifiers agree on the canonical bytes. String
+/// values must be valid UTF-8, otherwise `InvalidUtf8` is returned. The
+/// message is also checked by `validate_typed_message_structure` before any
+/// hashing happens.
+pub fn build_typed_message_hash_strict<'r>(
+    typed_message: &TypedMessageReader<'r>,
+) -> Result<[u8; 32], Error> {
+    validate_typed_message_structure(typed_message)?;
+    let options = HashOptions {
+        strict: true,
+        ..Default::default()
+    };
+    build_typed_message_hash_inner::<Keccak256>(typed_message, EIP712_PREFIX, options)
+}
+
+/// Same as `build_typed_message_hash`, but every RefCell & RefBytes in the
+/// message, including the domain separator and nested structs, must point
+/// at cells that already exist on chain. Referencing Output / GroupOutput
+/// cells yields `OutputRefCell`, see `fetch_hash_restricted`.
+pub fn build_typed_message_hash_restricted<'r>(
+    typed_message: &TypedMessageReader<'r>,
+) -> Result<[u8; 32], Error> {
+    let options = HashOptions {
+        restricted: true,
+        ..Default::default()
     };
+    build_typed_message_hash_inner::<Keccak256>(typed_message, EIP712_PREFIX, options)
+}
 
-    let mut hasher = Keccak256::default();
-    hasher.update(b"\x19\x01");
-    hasher.update(fetch_hash(&eip712.domain_separator())?);
-    hasher.update(hash_struct(&eip712.message())?);
+/// Same as `build_typed_message_hash_strict`, but also checks each struct
+/// holds exactly the number of values its type defines, so a signer can
+/// neither omit nor append fields. `expected_field_count` maps a struct type
+/// hash to its number of fields, usually from the caller's type registry,
+/// see `validate_field_counts` for details.
+pub fn build_typed_message_hash_with_field_counts<'r, F>(
+    typed_message: &TypedMessageReader<'r>,
+    expected_field_count: F,
+) -> Result<[u8; 32], Error>
+where
+    F: Fn(&[u8; 32]) -> Option<usize>,
+{
+    validate_field_counts(typed_message, expected_field_count)?;
+    build_typed_message_hash_strict(typed_message)
+}
+
+/// Checks the number of values in every struct of a typed message against
+/// `expected_field_count`, which is called with the resolved type hash of
+/// each struct. `FieldCountMismatch` is returned for the first struct in
+/// mismatch. Structs whose type hash yields `None` are not checked.
+///
+/// ```
+/// use ckb_typed_message_signing::{
+///     eip712::{validate_field_counts, Error},
+///     schemas::basic::*,
+/// };
+/// use molecule::prelude::{Builder, Byte, Entity};
+///
+/// let hash = |h: [u8; 32]| {
+///     Hash::new_builder()
+///         .set(Byte32::new_unchecked(h.to_vec().into()))
+///         .build()
+/// };
+/// let message = |fields: usize| {
+///     let value = Value::new_builder()
+///         .set(Bool::new_unchecked(vec![1].into()))
+///         .build();
+///     let value = Bytes::new_builder()
+///         .set(value.as_slice().iter().map(|b| Byte::new(*b)).collect())
+///         .build();
+///     let values = vec![value; fields];
+///     let message = Struct::new_builder()
+///         .type_hash(hash([1u8; 32]))
+///         .values(SerializedValues::new_builder().set(values).build())
+///         .build();
+///     TypedMessage::new_builder()
+///         .set(
+///             EIP712::new_builder()
+///                 .domain_separator(hash([0u8; 32]))
+///                 .message(message)
+///                 .build(),
+///         )
+///         .build()
+/// };
+/// let expected = |h: &[u8; 32]| (h == &[1u8; 32]).then_some(2);
+///
+/// assert_eq!(validate_field_counts(&message(2).as_reader(), expected), Ok(()));
+/// assert_eq!(
+///     validate_field_counts(&message(1).as_reader(), expected),
+///     Err(Error::FieldCountMismatch(0))
+/// );
+/// assert_eq!(
+///     validate_field_counts(&message(3).as_reader(), expected),
+///     Err(Error::FieldCountMismatch(0))
+/// );
+/// assert_eq!(validate_field_counts(&message(3).as_reader(), |_| None), Ok(()));
+/// ```
+pub fn validate_field_counts<'r, F>(
+    typed_message: &TypedMessageReader<'r>,
+    expected_field_count: F,
+) -> Result<(), Error>
+where
+    F: Fn(&[u8; 32]) -> Option<usize>,
+{
+    let TypedMessageUnionReader::EIP712(eip712) = typed_message.to_enum();
+    let message = eip712.message();
+    let expected = expected_field_count(&fetch_hash(&message.type_hash())?);
+    check_field_counts(&message.values(), expected, &expected_field_count, &mut 0)
+}
+
+// Structs & arrays are numbered the same way as in `hash_struct`, `expected`
+// is the number of values required for `values` itself.
+fn check_field_counts<F>(
+    values: &SerializedValuesReader,
+    expected: Option<usize>,
+    expected_field_count: &F,
+    next_struct: &mut usize,
+) -> Result<(), Error>
+where
+    F: Fn(&[u8; 32]) -> Option<usize>,
+{
+    let struct_index = *next_struct;
+    *next_struct += 1;
+    if expected.is_some_and(|n| n != values.len()) {
+        return Err(Error::FieldCountMismatch(struct_index));
+    }
+    for i in 0..values.len() {
+        let serialized_value = values.get_unchecked(i);
+        match ValueReader::from_slice(serialized_value.raw_data())?.to_enum() {
+            ValueUnionReader::Struct(s) => {
+                let expected = expected_field_count(&fetch_hash(&s.type_hash())?);
+                check_field_counts(&s.values(), expected, expected_field_count, next_struct)?
+            }
+            ValueUnionReader::Array(a) => {
+                check_field_counts(&a.values(), None, expected_field_count, next_struct)?
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Maximum nesting level of structs & arrays accepted by
+/// `validate_typed_message_structure`
+pub const MAX_VALUE_DEPTH: usize = 32;
+
+/// Walks the whole value tree of a typed message once, checking the same
+/// per-value invariants enforced while hashing, without doing any hashing
+/// or syscalls: serialized values must be valid molecule Value, Bool must be
+/// 0 or 1, Int & Uint must be 1 to 32 bytes, FixedBytes must be 1 to 32
+/// bytes. Structs & arrays nested deeper than `MAX_VALUE_DEPTH` are rejected
+/// with `ValueTooDeep`. Referenced hashes are not resolved, so a message
+/// passing validation might still fail hashing on those.
+pub fn validate_typed_message_structure<'r>(
+    typed_message: &TypedMessageReader<'r>,
+) -> Result<(), Error> {
+    let TypedMessageUnionReader::EIP712(eip712) = typed_message.to_enum();
+    validate_values(&eip712.message().values(), 1, &mut 0)
+}
+
+/// A hash in typed message resolved from transaction data rather than
+/// given literally
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum HashReference {
+    Cell {
+        source: u64,
+        index: u32,
+        offset: u32,
+        keccak: bool,
+    },
+    Transaction {
+        offset: u32,
+        length: u32,
+        keccak: bool,
+    },
+    /// Bytes value referenced via RefBytes
+    Bytes {
+        source: u64,
+        index: u32,
+        offset: u32,
+        length: u32,
+    },
+}
+
+/// Collects all RefCell & RefTransaction hashes, as well as RefBytes values
+/// in a typed message, in the order they would be resolved when building
+/// EIP-712 hash. Tooling can use this to check the referenced data exists
+/// before hashing.
+pub fn collect_hash_references<'r>(
+    typed_message: &TypedMessageReader<'r>,
+) -> Result<Vec<HashReference>, Error> {
+    let TypedMessageUnionReader::EIP712(eip712) = typed_message.to_enum();
+    let mut references = Vec::new();
+    collect_hash_reference(&eip712.domain_separator(), &mut references)?;
+    collect_struct_references(&eip712.message(), &mut references)?;
+    Ok(references)
+}
+
+fn collect_struct_references(
+    s: &StructReader,
+    references: &mut Vec<HashReference>,
+) -> Result<(), Error> {
+    collect_hash_reference(&s.type_hash(), references)?;
+    collect_values_references(&s.values(), references)
+}
+
+fn collect_values_references(
+    values: &SerializedValuesReader,
+    references: &mut Vec<HashReference>,
+) -> Result<(), Error> {
+    for i in 0..values.len() {
+        let serialized_value = values.get_unchecked(i);
+        let value = ValueReader::from_slice(serialized_value.raw_data())?;
+        match value.to_enum() {
+            ValueUnionReader::Struct(s) => collect_struct_references(&s, references)?,
+            ValueUnionReader::Array(a) => collect_values_references(&a.values(), references)?,
+            ValueUnionReader::RefBytes(r) => {
+                let mut t = [0u8; 8];
+                t.copy_from_slice(r.source().raw_data());
+                references.push(HashReference::Bytes {
+                    source: u64::from_le_bytes(t),
+                    index: read_u32(&r.index()),
+                    offset: read_u32(&r.offset()),
+                    length: read_u32(&r.length()),
+                });
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn collect_hash_reference(
+    h: &HashReader,
+    references: &mut Vec<HashReference>,
+) -> Result<(), Error> {
+    match h.to_enum() {
+        // The executing script always exists, there is nothing to check
+        HashUnionReader::Byte32(_) | HashUnionReader::RefScript(_) => (),
+        HashUnionReader::RefCell(r) => {
+            let mut t = [0u8; 8];
+            t.copy_from_slice(r.source().raw_data());
+            references.push(HashReference::Cell {
+                source: u64::from_le_bytes(t),
+                index: read_u32(&r.index()),
+                offset: read_u32(&r.offset()),
+                keccak: read_bool(&r.keccak()).ok_or(Error::InvalidRefFlag)?,
+            });
+        }
+        HashUnionReader::RefTransaction(r) => references.push(HashReference::Transaction {
+            offset: read_u32(&r.offset()),
+            length: read_u32(&r.length()),
+            keccak: read_bool(&r.keccak()).ok_or(Error::InvalidRefFlag)?,
+        }),
+    }
+    Ok(())
+}
+
+fn read_u32(n: &Uint32Reader) -> u32 {
+    let mut t = [0u8; 4];
+    t.copy_from_slice(n.raw_data());
+    u32::from_le_bytes(t)
+}
+
+// `next_struct` is the struct_index to be assigned to the next struct or
+// array visited, in the same order as hashing.
+fn validate_values(
+    values: &SerializedValuesReader,
+    depth: usize,
+    next_struct: &mut usize,
+) -> Result<(), Error> {
+    if depth > MAX_VALUE_DEPTH {
+        return Err(Error::ValueTooDeep);
+    }
+    let struct_index = *next_struct;
+    *next_struct += 1;
+    for i in 0..values.len() {
+        let path = FieldPath {
+            struct_index,
+            value_index: i,
+        };
+        let serialized_value = values.get_unchecked(i);
+        let value = ValueReader::from_slice(serialized_value.raw_data())?;
+        match value.to_enum() {
+            ValueUnionReader::Struct(s) => validate_values(&s.values(), depth + 1, next_struct)?,
+            ValueUnionReader::Array(a) => validate_values(&a.values(), depth + 1, next_struct)?,
+            ValueUnionReader::Bool(b) => {
+                read_bool(&b).ok_or(Error::InvalidBool(path))?;
+            }
+            ValueUnionReader::Int(n) if n.is_empty() || n.len() > 32 => {
+                return Err(Error::InvalidNumber(path))
+            }
+            ValueUnionReader::Uint(n) if n.is_empty() || n.len() > 32 => {
+                return Err(Error::InvalidNumber(path))
+            }
+            ValueUnionReader::FixedBytes(f) if f.is_empty() || f.len() > 32 => {
+                return Err(Error::InvalidFixedBytes(path))
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn build_typed_message_hash_inner<'r, D>(
+    typed_message: &TypedMessageReader<'r>,
+    prefix: &[u8],
+    options: HashOptions,
+) -> Result<[u8; 32], Error>
+where
+    D: Digest + Default + OutputSizeUser<OutputSize = U32>,
+{
+    let TypedMessageUnionReader::EIP712(eip712) = typed_message.to_enum();
+
+    let mut hasher = D::default();
+    hasher.update(prefix);
+    hasher.update(fetch_hash_with(
+        &eip712.domain_separator(),
+        options.restricted,
+    )?);
+    hasher.update(hash_struct::<D>(&eip712.message(), options, &mut 0)?);
     let mut result = [0u8; 32];
     result.copy_from_slice(&hasher.finalize());
     Ok(result)
 }
 
+/// Returns the domain separator of an EIP-712 typed message, resolving
+/// referenced hashes the same way as `build_typed_message_hash`. Wallets can
+/// use this to show which domain a message belongs to before approval.
+pub fn domain_separator_hash<'r>(eip712: &EIP712Reader<'r>) -> Result<[u8; 32], Error> {
+    fetch_hash(&eip712.domain_separator())
+}
+
+/// Returns the number of values in the top-level message struct, allowing
+/// a script to reject messages not matching an expected template early.
+pub fn message_field_count<'r>(typed_message: &TypedMessageReader<'r>) -> Result<usize, Error> {
+    let TypedMessageUnionReader::EIP712(eip712) = typed_message.to_enum();
+    Ok(eip712.message().values().len())
+}
+
+/// keccak256("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)")
+pub const EIP712_DOMAIN_TYPE_HASH: [u8; 32] = [
+    0x8b, 0x73, 0xc3, 0xc6, 0x9b, 0xb8, 0xfe, 0x3d, 0x51, 0x2e, 0xcc, 0x4c, 0xf7, 0x59, 0xcc, 0x79,
+    0x23, 0x9f, 0x7b, 0x17, 0x9b, 0x0f, 0xfa, 0xca, 0xa9, 0xa7, 0x5d, 0x52, 0x2b, 0x39, 0x40, 0x0f,
+];
+
+/// Computes EIP-712 domain separator from a structured domain, the same
+/// way `hashStruct` works on an EIP712Domain value with all four fields.
+///
+/// ```
+/// use ckb_typed_message_signing::{eip712::hash_domain, schemas::basic::*};
+/// use molecule::prelude::{Builder, Byte, Entity};
+///
+/// let bytes = |s: &[u8]| s.iter().map(|b| Byte::new(*b)).collect::<Vec<_>>();
+/// let mut chain_id = [0u8; 32];
+/// chain_id[31] = 1;
+/// let domain = EIP712Domain::new_builder()
+///     .name(String::new_builder().set(bytes(b"Ether Mail")).build())
+///     .version(String::new_builder().set(bytes(b"1")).build())
+///     .chain_id(Byte32::new_unchecked(chain_id.to_vec().into()))
+///     .verifying_contract(Address::new_unchecked(vec![0xcc; 20].into()))
+///     .build();
+/// // Domain separator of the Mail example in EIP-712 spec
+/// assert_eq!(
+///     hash_domain(&domain.as_reader()),
+///     [
+///         0xf2, 0xce, 0xe3, 0x75, 0xfa, 0x42, 0xb4, 0x21, 0x43, 0x80, 0x40, 0x25, 0xfc, 0x44,
+///         0x9d, 0xea, 0xfd, 0x50, 0xcc, 0x03, 0x1c, 0xa2, 0x57, 0xe0, 0xb1, 0x94, 0xa6, 0x50,
+///         0xa9, 0x12, 0x09, 0x0f,
+///     ]
+/// );
+/// ```
+pub fn hash_domain(domain: &EIP712DomainReader) -> [u8; 32] {
+    let mut hasher = Keccak256::default();
+    hasher.update(EIP712_DOMAIN_TYPE_HASH);
+    hasher.update(Keccak256::digest(domain.name().raw_data()));
+    hasher.update(Keccak256::digest(domain.version().raw_data()));
+    hasher.update(domain.chain_id().raw_data());
+    hasher.update([0u8; 12]);
+    hasher.update(domain.verifying_contract().raw_data());
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&hasher.finalize());
+    result
+}
+
 // Ouch
 fn u64_to_source(source: u64) -> Result<Source, Error> {
     match source {
@@ -74,22 +675,125 @@ fn u64_to_source(source: u64) -> Result<Source, Error> {
         s if s == Source::HeaderDep as u64 => Ok(Source::HeaderDep),
         s if s == Source::GroupInput as u64 => Ok(Source::GroupInput),
         s if s == Source::GroupOutput as u64 => Ok(Source::GroupOutput),
-        _ => Err(Error::InvalidSource),
+        s => Err(Error::InvalidSource(s)),
+    }
+}
+
+/// Same as resolving a hash in `build_typed_message_hash`, but RefCell
+/// pointing at Output / GroupOutput cells is rejected with `OutputRefCell`.
+///
+/// Referencing an output cell commits to data the signer is producing in
+/// current transaction, which is fine for sighash purposes as outputs are
+/// covered by tx hash anyway. Still, a script wanting hashes to come only
+/// from data that already exists on chain can use this variant, or
+/// `build_typed_message_hash_restricted` to restrict a whole message.
+///
+/// ```
+/// # #[cfg(feature = "mock-syscalls")] {
+/// use ckb_std::ckb_constants::Source;
+/// use ckb_typed_message_signing::{
+///     eip712::{fetch_hash_restricted, Error},
+///     schemas::basic::*,
+///     MockTransaction,
+/// };
+/// use molecule::prelude::{Builder, Entity};
+///
+/// MockTransaction {
+///     input_count: 1,
+///     input_cell_data: vec![vec![3u8; 32]],
+///     transaction: vec![5u8; 64],
+///     ..Default::default()
+/// }
+/// .install();
+///
+/// let ref_cell = |index: u32| {
+///     let ref_cell = RefCell::new_builder()
+///         .source(Uint64::new_unchecked((Source::Input as u64).to_le_bytes().to_vec().into()))
+///         .index(Uint32::new_unchecked(index.to_le_bytes().to_vec().into()))
+///         .build();
+///     Hash::new_builder().set(ref_cell).build()
+/// };
+/// assert_eq!(fetch_hash_restricted(&ref_cell(0).as_reader()), Ok([3u8; 32]));
+/// assert_eq!(
+///     fetch_hash_restricted(&ref_cell(1).as_reader()),
+///     Err(Error::RefCellIndexOutOfBound)
+/// );
+///
+/// let ref_tx = |offset: u32| {
+///     let ref_tx = RefTransaction::new_builder()
+///         .offset(Uint32::new_unchecked(offset.to_le_bytes().to_vec().into()))
+///         .length(Uint32::new_unchecked(32u32.to_le_bytes().to_vec().into()))
+///         .build();
+///     Hash::new_builder().set(ref_tx).build()
+/// };
+/// assert_eq!(fetch_hash_restricted(&ref_tx(32).as_reader()), Ok([5u8; 32]));
+/// assert_eq!(
+///     fetch_hash_restricted(&ref_tx(40).as_reader()),
+///     Err(Error::CellDataEof)
+/// );
+/// assert_eq!(
+///     fetch_hash_restricted(&ref_tx(64).as_reader()),
+///     Err(Error::RefTransactionOutOfBound)
+/// );
+/// # }
+/// ```
+pub fn fetch_hash_restricted<'r>(h: &HashReader<'r>) -> Result<[u8; 32], Error> {
+    fetch_hash_with(h, true)
+}
+
+pub(crate) fn fetch_hash<'r>(h: &HashReader<'r>) -> Result<[u8; 32], Error> {
+    fetch_hash_with(h, false)
+}
+
+// Rejects output sources when `restricted` is set
+fn check_restricted(source: Source, restricted: bool) -> Result<(), Error> {
+    if restricted && matches!(source, Source::Output | Source::GroupOutput) {
+        return Err(Error::OutputRefCell);
     }
+    Ok(())
 }
 
-fn fetch_hash<'r>(h: &HashReader<'r>) -> Result<[u8; 32], Error> {
+// For RefCell, `source` & `index` are passed to load_cell_data syscall
+// untouched. With GroupInput / GroupOutput, CKB resolves `index` within
+// current script group, so index 0 refers to the first cell of the group,
+// not the first cell in the transaction. The same rules apply to all
+// sources: the 32 bytes starting at `offset` must lie fully within cell
+// data, otherwise `CellDataEof` is returned. A cell not existing in its
+// source yields `RefCellIndexOutOfBound`.
+//
+// For RefTransaction, `offset` at or beyond the end of the transaction
+// yields `RefTransactionOutOfBound`, while a field starting within the
+// transaction but running past its end yields `CellDataEof`.
+//
+// RefScript resolves to the hash of the executing script, it is only
+// meaningful within a script, there is no such script off-chain.
+//
+// With `keccak` flag set, the referenced bytes are hashed with keccak256
+// to form the result: all cell data starting from `offset` for RefCell,
+// `length` bytes starting from `offset` for RefTransaction. The flag always
+// means keccak256 as named in the schema, even when the message itself is
+// hashed with another digest via `build_typed_message_hash_with`. The
+// referenced bytes are streamed in chunks, so memory usage stays bounded
+// no matter how large a `length` or cell the message asks for.
+//
+// With `restricted` set, RefCell pointing at output cells yields
+// `OutputRefCell`, see `fetch_hash_restricted`.
+fn fetch_hash_with<'r>(h: &HashReader<'r>, restricted: bool) -> Result<[u8; 32], Error> {
     let mut result = [0u8; 32];
     match h.to_enum() {
         HashUnionReader::Byte32(hash) => {
-            result.copy_from_slice(&hash.raw_data());
+            result.copy_from_slice(hash.raw_data());
+        }
+        HashUnionReader::RefScript(_) => {
+            result = load_script_hash()?;
         }
         HashUnionReader::RefCell(ref_cell) => {
             let source = {
                 let mut t = [0u8; 8];
                 t.copy_from_slice(ref_cell.source().raw_data());
-                u64::from_le_bytes(t)
+                u64_to_source(u64::from_le_bytes(t))?
             };
+            check_restricted(source, restricted)?;
             let index = {
                 let mut t = [0u8; 4];
                 t.copy_from_slice(ref_cell.index().raw_data());
@@ -100,19 +804,30 @@ fn fetch_hash<'r>(h: &HashReader<'r>) -> Result<[u8; 32], Error> {
                 t.copy_from_slice(ref_cell.offset().raw_data());
                 u32::from_le_bytes(t)
             };
-            match load_cell_data(
-                &mut result,
-                offset as usize,
-                index as usize,
-                u64_to_source(source)?,
-            ) {
+            if read_bool(&ref_cell.keccak()).ok_or(Error::InvalidRefFlag)? {
+                let data_length = match load_cell_data(&mut [], 0, index as usize, source) {
+                    Ok(n) => n,
+                    Err(SysError::LengthNotEnough(n)) => n,
+                    Err(e) => return Err(ref_cell_error(e)),
+                };
+                let length = data_length
+                    .checked_sub(offset as usize)
+                    .ok_or(Error::CellDataEof)?;
+                let mut hasher = Keccak256::default();
+                stream_cell_data(index as usize, source, offset as usize, length, |chunk| {
+                    hasher.update(chunk)
+                })?;
+                result.copy_from_slice(&hasher.finalize());
+                return Ok(result);
+            }
+            match load_cell_data(&mut result, offset as usize, index as usize, source) {
                 Ok(n) => {
                     if n < 32 {
                         return Err(Error::CellDataEof);
                     }
                 }
                 Err(SysError::LengthNotEnough(_)) => (),
-                Err(e) => return Err(e.into()),
+                Err(e) => return Err(ref_cell_error(e)),
             }
         }
         HashUnionReader::RefTransaction(ref_tx) => {
@@ -121,9 +836,26 @@ fn fetch_hash<'r>(h: &HashReader<'r>) -> Result<[u8; 32], Error> {
                 t.copy_from_slice(ref_tx.offset().raw_data());
                 u32::from_le_bytes(t)
             };
-            match load_transaction(&mut result, offset as usize) {
+            let length = {
+                let mut t = [0u8; 4];
+                t.copy_from_slice(ref_tx.length().raw_data());
+                u32::from_le_bytes(t) as usize
+            };
+            if read_bool(&ref_tx.keccak()).ok_or(Error::InvalidRefFlag)? {
+                let mut hasher = Keccak256::default();
+                stream_transaction(offset as usize, length, |chunk| hasher.update(chunk))?;
+                result.copy_from_slice(&hasher.finalize());
+                return Ok(result);
+            }
+            if length == 0 || length > 32 {
+                return Err(Error::InvalidRefLength);
+            }
+            // Fields narrower than 32 bytes are left padded with zeros, the
+            // same way as numbers are encoded.
+            match load_transaction(&mut result[(32 - length)..], offset as usize) {
+                Ok(0) => return Err(Error::RefTransactionOutOfBound),
                 Ok(n) => {
-                    if n < 32 {
+                    if n < length {
                         return Err(Error::CellDataEof);
                     }
                 }
@@ -135,77 +867,265 @@ fn fetch_hash<'r>(h: &HashReader<'r>) -> Result<[u8; 32], Error> {
     Ok(result)
 }
 
-fn hash_struct(s: &StructReader) -> Result<[u8; 32], Error> {
-    let mut hasher = Keccak256::default();
-    hasher.update(fetch_hash(&s.type_hash())?);
+// Cell loading syscalls report a missing cell as IndexOutOfBound
+fn ref_cell_error(e: SysError) -> Error {
+    match e {
+        SysError::IndexOutOfBound => Error::RefCellIndexOutOfBound,
+        e => e.into(),
+    }
+}
+
+// Options threaded through hashing of a typed message
+#[derive(Clone, Copy, Default)]
+struct HashOptions {
+    // See `build_typed_message_hash_strict`
+    strict: bool,
+    // See `build_typed_message_hash_restricted`
+    restricted: bool,
+}
+
+pub(crate) fn read_bool(b: &BoolReader) -> Option<bool> {
+    match b.raw_data()[0] {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
+}
+
+// `next_struct` is the struct_index to be assigned to the next struct or
+// array visited, see FieldPath.
+fn hash_struct<D>(
+    s: &StructReader,
+    options: HashOptions,
+    next_struct: &mut usize,
+) -> Result<[u8; 32], Error>
+where
+    D: Digest + Default + OutputSizeUser<OutputSize = U32>,
+{
+    let struct_index = *next_struct;
+    *next_struct += 1;
+    let mut hasher = D::default();
+    hasher.update(fetch_hash_with(&s.type_hash(), options.restricted)?);
     for i in 0..s.values().len() {
         let serialized_value = s.values().get_unchecked(i);
         let value = ValueReader::from_slice(serialized_value.raw_data())?;
-        encode_value(&mut hasher, &value)?;
+        let path = FieldPath {
+            struct_index,
+            value_index: i,
+        };
+        encode_value(&mut hasher, &value, options, path, next_struct)?;
     }
     let mut result = [0u8; 32];
     result.copy_from_slice(&hasher.finalize());
     Ok(result)
 }
 
-fn encode_value<'r, D: Digest>(hasher: &mut D, value: &ValueReader<'r>) -> Result<(), Error> {
+// `path` is the location of `value` itself, reported in errors
+fn encode_value<'r, D>(
+    hasher: &mut D,
+    value: &ValueReader<'r>,
+    options: HashOptions,
+    path: FieldPath,
+    next_struct: &mut usize,
+) -> Result<(), Error>
+where
+    D: Digest + Default + OutputSizeUser<OutputSize = U32>,
+{
     match value.to_enum() {
         ValueUnionReader::Struct(s) => {
-            let hash = hash_struct(&s)?;
+            let hash = hash_struct::<D>(&s, options, next_struct)?;
             hasher.update(hash);
         }
         ValueUnionReader::Array(a) => {
+            let struct_index = *next_struct;
+            *next_struct += 1;
             for i in 0..a.values().len() {
                 let serialized_value = a.values().get_unchecked(i);
                 let value = ValueReader::from_slice(serialized_value.raw_data())?;
-                encode_value(hasher, &value)?;
+                let path = FieldPath {
+                    struct_index,
+                    value_index: i,
+                };
+                encode_value(hasher, &value, options, path, next_struct)?;
             }
         }
         ValueUnionReader::Bool(b) => {
-            if b.raw_data()[0] != 0 && b.raw_data()[0] != 1 {
-                return Err(Error::InvalidBool);
-            }
-            encode_number(hasher, b.raw_data(), false)?;
+            read_bool(&b).ok_or(Error::InvalidBool(path))?;
+            encode_number(hasher, b.raw_data(), false, path)?;
         }
         ValueUnionReader::Bytes(b) => {
-            let mut hasher2 = Keccak256::default();
+            let mut hasher2 = D::default();
             hasher2.update(b.raw_data());
             let mut result = [0u8; 32];
             result.copy_from_slice(&hasher2.finalize());
             hasher.update(result);
         }
         ValueUnionReader::String(s) => {
-            let mut hasher2 = Keccak256::default();
+            // EIP-712 strings are UTF-8, only checked in strict mode
+            if options.strict && core::str::from_utf8(s.raw_data()).is_err() {
+                return Err(Error::InvalidUtf8);
+            }
+            let mut hasher2 = D::default();
             hasher2.update(s.raw_data());
             let mut result = [0u8; 32];
             result.copy_from_slice(&hasher2.finalize());
             hasher.update(result);
         }
+        ValueUnionReader::RefBytes(r) => {
+            let mut hasher2 = D::default();
+            load_ref_bytes(&r, options.restricted, |chunk| hasher2.update(chunk))?;
+            let mut result = [0u8; 32];
+            result.copy_from_slice(&hasher2.finalize());
+            hasher.update(result);
+        }
         ValueUnionReader::Address(a) => {
             // Address is treated as uint160
-            encode_number(hasher, a.raw_data(), false)?;
+            encode_number(hasher, a.raw_data(), false, path)?;
         }
         ValueUnionReader::FixedBytes(f) => {
-            if f.len() > 32 {
-                return Err(Error::InvalidFixedBytes);
+            // FixedBytes represents Solidity bytes1 to bytes32, which are
+            // left aligned and padded with zeros on the right. This differs
+            // from numbers, which are right aligned. There is no bytes0, an
+            // empty FixedBytes would be indistinguishable from zero bytes.
+            if f.is_empty() || f.len() > 32 {
+                return Err(Error::InvalidFixedBytes(path));
             }
             let mut data = [0u8; 32];
             data[0..f.len()].copy_from_slice(f.raw_data());
             hasher.update(data);
         }
         ValueUnionReader::Int(i) => {
-            encode_number(hasher, i.raw_data(), true)?;
+            if options.strict {
+                encode_number_strict(hasher, i.raw_data(), true, path)?;
+            } else {
+                encode_number(hasher, i.raw_data(), true, path)?;
+            }
         }
         ValueUnionReader::Uint(u) => {
-            encode_number(hasher, u.raw_data(), false)?;
+            if options.strict {
+                encode_number_strict(hasher, u.raw_data(), false, path)?;
+            } else {
+                encode_number(hasher, u.raw_data(), false, path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Size of each chunk loaded for RefBytes, bounding memory usage regardless
+// of the referenced length
+const REF_BYTES_CHUNK: usize = 1024;
+
+// Feeds the bytes referenced by RefBytes to `f` chunk by chunk. Like
+// RefCell, `CellDataEof` is returned when cell data ends before `length`
+// bytes are read. Output cells are rejected when `restricted` is set.
+fn load_ref_bytes<F: FnMut(&[u8])>(
+    r: &RefBytesReader,
+    restricted: bool,
+    f: F,
+) -> Result<(), Error> {
+    let source = {
+        let mut t = [0u8; 8];
+        t.copy_from_slice(r.source().raw_data());
+        u64_to_source(u64::from_le_bytes(t))?
+    };
+    check_restricted(source, restricted)?;
+    let index = {
+        let mut t = [0u8; 4];
+        t.copy_from_slice(r.index().raw_data());
+        u32::from_le_bytes(t) as usize
+    };
+    let offset = {
+        let mut t = [0u8; 4];
+        t.copy_from_slice(r.offset().raw_data());
+        u32::from_le_bytes(t) as usize
+    };
+    let length = {
+        let mut t = [0u8; 4];
+        t.copy_from_slice(r.length().raw_data());
+        u32::from_le_bytes(t) as usize
+    };
+
+    stream_cell_data(index, source, offset, length, f)
+}
+
+// Feeds `length` bytes of cell data starting at `offset` to `f`, at most
+// REF_BYTES_CHUNK bytes at a time
+fn stream_cell_data<F: FnMut(&[u8])>(
+    index: usize,
+    source: Source,
+    offset: usize,
+    length: usize,
+    mut f: F,
+) -> Result<(), Error> {
+    let mut buf = [0u8; REF_BYTES_CHUNK];
+    let mut loaded = 0;
+    while loaded < length {
+        let chunk = core::cmp::min(REF_BYTES_CHUNK, length - loaded);
+        match load_cell_data(&mut buf[..chunk], offset + loaded, index, source) {
+            Ok(n) => {
+                if n < chunk {
+                    return Err(Error::CellDataEof);
+                }
+            }
+            Err(SysError::LengthNotEnough(_)) => (),
+            Err(e) => return Err(ref_cell_error(e)),
+        }
+        f(&buf[..chunk]);
+        loaded += chunk;
+    }
+    Ok(())
+}
+
+// Feeds `length` bytes of current transaction starting at `offset` to `f`,
+// at most REF_BYTES_CHUNK bytes at a time. Like RefTransaction, `offset` at
+// or beyond the end of the transaction yields `RefTransactionOutOfBound`,
+// while running past its end yields `CellDataEof`.
+fn stream_transaction<F: FnMut(&[u8])>(
+    offset: usize,
+    length: usize,
+    mut f: F,
+) -> Result<(), Error> {
+    let mut buf = [0u8; REF_BYTES_CHUNK];
+    let mut loaded = 0;
+    while loaded < length {
+        let chunk = core::cmp::min(REF_BYTES_CHUNK, length - loaded);
+        match load_transaction(&mut buf[..chunk], offset + loaded) {
+            Ok(0) if loaded == 0 => return Err(Error::RefTransactionOutOfBound),
+            Ok(n) => {
+                if n < chunk {
+                    return Err(Error::CellDataEof);
+                }
+            }
+            Err(SysError::LengthNotEnough(_)) => (),
+            Err(e) => return Err(e.into()),
         }
+        f(&buf[..chunk]);
+        loaded += chunk;
     }
     Ok(())
 }
 
-fn encode_number<D: Digest>(hasher: &mut D, n: &[u8], signed: bool) -> Result<(), Error> {
-    if n.len() > 32 {
-        return Err(Error::InvalidNumber);
+// Numbers are kept in big-endian two's complement(see buildValue in the
+// TypeScript library), so the sign bit always lives in n[0]. Sign extending
+// with 0xFF for negative values yields the same 32-byte word as Solidity's
+// abi encoding of intN.
+fn encode_number<D: Digest>(
+    hasher: &mut D,
+    n: &[u8],
+    signed: bool,
+    path: FieldPath,
+) -> Result<(), Error> {
+    hasher.update(extend_number(n, signed, path)?);
+    Ok(())
+}
+
+// Extends a big-endian number to a 32-byte word, sign extended if `signed`
+fn extend_number(n: &[u8], signed: bool, path: FieldPath) -> Result<[u8; 32], Error> {
+    // An empty number has no sign byte to extend, zero must be encoded
+    // with at least one byte.
+    if n.is_empty() || n.len() > 32 {
+        return Err(Error::InvalidNumber(path));
     }
     let fill = if signed {
         if n[0] & 0x80 != 0 {
@@ -218,6 +1138,859 @@ fn encode_number<D: Digest>(hasher: &mut D, n: &[u8], signed: bool) -> Result<()
     };
     let mut data = [fill; 32];
     data[(32 - n.len())..32].copy_from_slice(n);
-    hasher.update(data);
-    Ok(())
+    Ok(data)
+}
+
+fn encode_number_strict<D: Digest>(
+    hasher: &mut D,
+    n: &[u8],
+    signed: bool,
+    path: FieldPath,
+) -> Result<(), Error> {
+    if n.len() > 1 {
+        let redundant = if signed {
+            (n[0] == 0 && n[1] & 0x80 == 0) || (n[0] == 0xFF && n[1] & 0x80 != 0)
+        } else {
+            n[0] == 0
+        };
+        if redundant {
+            return Err(Error::InvalidNumber(path));
+        }
+    }
+    encode_number(hasher, n, signed, path)
+}
+
+/// Decodes an `Int` value as i128. Int is stored as big-endian two's
+/// complement of 1 to 32 bytes, sign extended the same way as when hashed.
+/// Returns `NumberTooLarge` if the value does not fit in 128 bits,
+/// `UnexpectedValueType` if the value is not an `Int`, and `InvalidNumber`
+/// if it has an invalid length. Since the value is not located in a typed
+/// message here, the path of `InvalidNumber` is always the default one.
+///
+/// ```
+/// use ckb_typed_message_signing::{eip712::{value_as_i128, Error}, schemas::basic::*};
+/// use molecule::prelude::{Builder, Byte, Entity};
+///
+/// let int = |n: &[u8]| {
+///     Value::new_builder()
+///         .set(Int::new_builder().set(n.iter().map(|b| Byte::new(*b)).collect()).build())
+///         .build()
+/// };
+/// assert_eq!(value_as_i128(&int(&[0xff]).as_reader()), Ok(-1));
+/// assert_eq!(value_as_i128(&int(&[0x00, 0x80]).as_reader()), Ok(128));
+///
+/// let mut max = [0u8; 32];
+/// max[16] = 0x7f;
+/// max[17..].fill(0xff);
+/// assert_eq!(value_as_i128(&int(&max).as_reader()), Ok(i128::MAX));
+/// let mut min = [0xffu8; 32];
+/// min[16..].fill(0);
+/// min[16] = 0x80;
+/// assert_eq!(value_as_i128(&int(&min).as_reader()), Ok(i128::MIN));
+///
+/// max[15] = 0x01;
+/// assert_eq!(value_as_i128(&int(&max).as_reader()), Err(Error::NumberTooLarge));
+/// assert_eq!(value_as_i128(&int(&max[15..]).as_reader()), Err(Error::NumberTooLarge));
+/// min[16] = 0x7f;
+/// assert_eq!(value_as_i128(&int(&min).as_reader()), Err(Error::NumberTooLarge));
+/// ```
+pub fn value_as_i128(value: &ValueReader) -> Result<i128, Error> {
+    let data = match value.to_enum() {
+        ValueUnionReader::Int(n) => extend_number(n.raw_data(), true, FieldPath::default())?,
+        _ => return Err(Error::UnexpectedValueType),
+    };
+    // The upper 16 bytes must only be sign extension of the lower 16 bytes
+    let fill = if data[16] & 0x80 != 0 { 0xFF } else { 0 };
+    if data[..16].iter().any(|b| *b != fill) {
+        return Err(Error::NumberTooLarge);
+    }
+    let mut lower = [0u8; 16];
+    lower.copy_from_slice(&data[16..]);
+    Ok(i128::from_be_bytes(lower))
+}
+
+/// Decodes a `Uint` value as u128. Uint is stored big-endian in 1 to 32
+/// bytes. Returns `NumberTooLarge` if the value does not fit in 128 bits,
+/// `UnexpectedValueType` if the value is not a `Uint`, and `InvalidNumber`
+/// if it has an invalid length.
+///
+/// ```
+/// use ckb_typed_message_signing::{eip712::{value_as_u128, Error}, schemas::basic::*};
+/// use molecule::prelude::{Builder, Byte, Entity};
+///
+/// let uint = |n: &[u8]| {
+///     Value::new_builder()
+///         .set(Uint::new_builder().set(n.iter().map(|b| Byte::new(*b)).collect()).build())
+///         .build()
+/// };
+/// assert_eq!(value_as_u128(&uint(&[0xff]).as_reader()), Ok(255));
+///
+/// let mut max = [0u8; 32];
+/// max[16..].fill(0xff);
+/// assert_eq!(value_as_u128(&uint(&max).as_reader()), Ok(u128::MAX));
+/// assert_eq!(value_as_u128(&uint(&max[16..]).as_reader()), Ok(u128::MAX));
+///
+/// max[15] = 0x01;
+/// assert_eq!(value_as_u128(&uint(&max).as_reader()), Err(Error::NumberTooLarge));
+/// assert_eq!(value_as_u128(&uint(&max[15..]).as_reader()), Err(Error::NumberTooLarge));
+/// ```
+pub fn value_as_u128(value: &ValueReader) -> Result<u128, Error> {
+    let data = match value.to_enum() {
+        ValueUnionReader::Uint(n) => extend_number(n.raw_data(), false, FieldPath::default())?,
+        _ => return Err(Error::UnexpectedValueType),
+    };
+    if data[..16].iter().any(|b| *b != 0) {
+        return Err(Error::NumberTooLarge);
+    }
+    let mut lower = [0u8; 16];
+    lower.copy_from_slice(&data[16..]);
+    Ok(u128::from_be_bytes(lower))
+}
+
+/// A single member in an EIP-712 struct type definition
+#[cfg(feature = "std")]
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct TypeMember {
+    pub name: String,
+    pub type_name: String,
+}
+
+/// EIP-712 struct type definitions, keyed by struct type name
+#[cfg(feature = "std")]
+pub type TypeDefinitions = BTreeMap<String, Vec<TypeMember>>;
+
+/// Builds the encodeType string of struct type `root` per EIP-712: `root`
+/// comes first, followed by all struct types referenced from it(directly or
+/// indirectly), sorted alphabetically. For example:
+/// `Mail(Person from,Person to,string contents)Person(string name,address wallet)`.
+/// Types not found in `types` are treated as atomic types.
+#[cfg(feature = "std")]
+pub fn encode_type_string(root: &str, types: &TypeDefinitions) -> String {
+    let mut dependencies = BTreeSet::new();
+    collect_dependencies(root, types, &mut dependencies);
+    dependencies.remove(root);
+
+    let mut result = String::new();
+    for type_name in core::iter::once(root).chain(dependencies.iter().map(|d| d.as_str())) {
+        result.push_str(type_name);
+        result.push('(');
+        if let Some(members) = types.get(type_name) {
+            for (i, member) in members.iter().enumerate() {
+                if i > 0 {
+                    result.push(',');
+                }
+                result.push_str(&member.type_name);
+                result.push(' ');
+                result.push_str(&member.name);
+            }
+        }
+        result.push(')');
+    }
+    result
+}
+
+/// Computes EIP-712 typeHash of struct type `root`, which is the keccak256
+/// hash of `encode_type_string`. The result can be used as a Byte32
+/// `type_hash` of Struct value.
+#[cfg(feature = "std")]
+pub fn type_hash(root: &str, types: &TypeDefinitions) -> [u8; 32] {
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&Keccak256::digest(
+        encode_type_string(root, types).as_bytes(),
+    ));
+    result
+}
+
+#[cfg(feature = "std")]
+fn collect_dependencies(type_name: &str, types: &TypeDefinitions, result: &mut BTreeSet<String>) {
+    // Array types like Person[] or Person[3] depend on the element type
+    let type_name = match type_name.find('[') {
+        Some(i) => &type_name[..i],
+        None => type_name,
+    };
+    if result.contains(type_name) {
+        return;
+    }
+    if let Some(members) = types.get(type_name) {
+        result.insert(String::from(type_name));
+        for member in members {
+            collect_dependencies(&member.type_name, types, result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        schemas::basic::{
+            Address, Bool, Byte32, Bytes, FixedBytes, Hash, Int, RefBytes, RefCell, RefScript,
+            RefTransaction, SerializedValues, String as MolString, Struct, Uint, Uint32, Uint64,
+            Value, EIP712,
+        },
+        MockTransaction,
+    };
+    use alloc::vec;
+    use ckb_std::ckb_types::{
+        packed::{BytesVec, RawTransaction, Script, Transaction},
+        prelude::{Pack, Unpack},
+    };
+    use molecule::prelude::{Builder, Byte, Entity};
+
+    fn bytes(data: &[u8]) -> Vec<Byte> {
+        data.iter().map(|b| Byte::new(*b)).collect()
+    }
+
+    // Hash of a single value as encoded by `encode_value`
+    fn encode(value: &Value) -> Result<[u8; 32], Error> {
+        let mut hasher = Keccak256::default();
+        encode_value(
+            &mut hasher,
+            &value.as_reader(),
+            HashOptions::default(),
+            FieldPath::default(),
+            &mut 0,
+        )?;
+        let mut result = [0u8; 32];
+        result.copy_from_slice(&hasher.finalize());
+        Ok(result)
+    }
+
+    // Hash of a 32-byte ABI word, as ethers-rs' `encode_data` produces it
+    fn word_hash(word: [u8; 32]) -> Result<[u8; 32], Error> {
+        let mut result = [0u8; 32];
+        result.copy_from_slice(&Keccak256::digest(word));
+        Ok(result)
+    }
+
+    fn word(prefix: &[u8], fill: u8) -> [u8; 32] {
+        let mut word = [fill; 32];
+        word[32 - prefix.len()..].copy_from_slice(prefix);
+        word
+    }
+
+    fn int(n: &[u8]) -> Value {
+        Value::new_builder()
+            .set(Int::new_builder().set(bytes(n)).build())
+            .build()
+    }
+
+    fn uint(n: &[u8]) -> Value {
+        Value::new_builder()
+            .set(Uint::new_builder().set(bytes(n)).build())
+            .build()
+    }
+
+    #[test]
+    fn test_encode_int8() {
+        assert_eq!(encode(&int(&[0xff])), word_hash([0xff; 32]));
+        assert_eq!(encode(&int(&[0x80])), word_hash(word(&[0x80], 0xff)));
+        assert_eq!(encode(&int(&[0x00])), word_hash([0; 32]));
+        assert_eq!(encode(&int(&[0x7f])), word_hash(word(&[0x7f], 0)));
+    }
+
+    #[test]
+    fn test_encode_int256() {
+        assert_eq!(encode(&int(&[0xff; 32])), word_hash([0xff; 32]));
+
+        let mut min = [0u8; 32];
+        min[0] = 0x80;
+        assert_eq!(encode(&int(&min)), word_hash(min));
+        let mut max = [0xffu8; 32];
+        max[0] = 0x7f;
+        assert_eq!(encode(&int(&max)), word_hash(max));
+        assert_eq!(encode(&int(&[0; 32])), word_hash([0; 32]));
+    }
+
+    #[test]
+    fn test_encode_positive_int_with_zero_high_byte() {
+        // 128 as int16: the leading zero byte keeps the value positive
+        assert_eq!(encode(&int(&[0x00, 0x80])), word_hash(word(&[0x80], 0)));
+        // -32768 as int16
+        assert_eq!(
+            encode(&int(&[0x80, 0x00])),
+            word_hash(word(&[0x80, 0x00], 0xff))
+        );
+    }
+
+    #[test]
+    fn test_encode_uint256() {
+        assert_eq!(encode(&uint(&[0xff; 32])), word_hash([0xff; 32]));
+        assert_eq!(encode(&uint(&[0xff])), word_hash(word(&[0xff], 0)));
+        assert_eq!(encode(&uint(&[0x00])), word_hash([0; 32]));
+        assert_eq!(
+            encode(&uint(&[0; 33])),
+            Err(Error::InvalidNumber(FieldPath::default()))
+        );
+        assert_eq!(
+            encode(&uint(&[])),
+            Err(Error::InvalidNumber(FieldPath::default()))
+        );
+    }
+
+    #[test]
+    fn test_encode_empty_int() {
+        // No sign byte to extend, rejected rather than panicking
+        assert_eq!(
+            encode(&int(&[])),
+            Err(Error::InvalidNumber(FieldPath::default()))
+        );
+    }
+
+    #[test]
+    fn test_encode_fixed_bytes() {
+        let fixed = |data: &[u8]| {
+            Value::new_builder()
+                .set(FixedBytes::new_builder().set(bytes(data)).build())
+                .build()
+        };
+        // bytesN is left aligned, unlike numbers
+        let mut bytes4 = [0u8; 32];
+        bytes4[..4].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(encode(&fixed(&[0xde, 0xad, 0xbe, 0xef])), word_hash(bytes4));
+        let mut bytes1 = [0u8; 32];
+        bytes1[0] = 0xab;
+        assert_eq!(encode(&fixed(&[0xab])), word_hash(bytes1));
+        assert_eq!(encode(&fixed(&[0xff; 32])), word_hash([0xff; 32]));
+        for data in [&[][..], &[0xff; 33][..]] {
+            assert_eq!(
+                encode(&fixed(data)),
+                Err(Error::InvalidFixedBytes(FieldPath::default()))
+            );
+        }
+    }
+
+    fn hash(h: [u8; 32]) -> Hash {
+        Hash::new_builder()
+            .set(Byte32::new_unchecked(h.to_vec().into()))
+            .build()
+    }
+
+    fn struct_of(type_hash: Hash, values: &[Value]) -> Struct {
+        let values = values
+            .iter()
+            .map(|v| Bytes::new_builder().set(bytes(v.as_slice())).build())
+            .collect();
+        Struct::new_builder()
+            .type_hash(type_hash)
+            .values(SerializedValues::new_builder().set(values).build())
+            .build()
+    }
+
+    fn typed_message(domain_separator: Hash, message: Struct) -> TypedMessage {
+        TypedMessage::new_builder()
+            .set(
+                EIP712::new_builder()
+                    .domain_separator(domain_separator)
+                    .message(message)
+                    .build(),
+            )
+            .build()
+    }
+
+    fn string(s: &str) -> Value {
+        Value::new_builder()
+            .set(MolString::new_builder().set(bytes(s.as_bytes())).build())
+            .build()
+    }
+
+    fn h256(hex: &str) -> [u8; 32] {
+        let mut result = [0u8; 32];
+        for (i, b) in result.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        result
+    }
+
+    // The Mail example from EIP-712 spec, with hashes from its reference
+    // implementation
+    #[test]
+    fn test_mail_example() {
+        let member = |name: &str, type_name: &str| TypeMember {
+            name: name.into(),
+            type_name: type_name.into(),
+        };
+        let mut types = TypeDefinitions::new();
+        types.insert(
+            "Mail".into(),
+            vec![
+                member("from", "Person"),
+                member("to", "Person"),
+                member("contents", "string"),
+            ],
+        );
+        types.insert(
+            "Person".into(),
+            vec![member("name", "string"), member("wallet", "address")],
+        );
+        assert_eq!(
+            encode_type_string("Mail", &types),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            type_hash("Mail", &types),
+            h256("a0cedeb2dc280ba39b857546d74f5549c3a1d7bdc2dd96bf881f76108e23dac2")
+        );
+
+        let person = |name: &str, wallet: [u8; 20]| {
+            let wallet = Value::new_builder()
+                .set(Address::new_unchecked(wallet.to_vec().into()))
+                .build();
+            let person = struct_of(hash(type_hash("Person", &types)), &[string(name), wallet]);
+            Value::new_builder().set(person).build()
+        };
+        let cow = [
+            0xcd, 0x2a, 0x3d, 0x9f, 0x93, 0x8e, 0x13, 0xcd, 0x94, 0x7e, 0xc0, 0x5a, 0xbc, 0x7f,
+            0xe7, 0x34, 0xdf, 0x8d, 0xd8, 0x26,
+        ];
+        let bob = [0xbb; 20];
+        let mail = struct_of(
+            hash(type_hash("Mail", &types)),
+            &[
+                person("Cow", cow),
+                person("Bob", bob),
+                string("Hello, Bob!"),
+            ],
+        );
+        assert_eq!(
+            hash_struct::<Keccak256>(&mail.as_reader(), HashOptions::default(), &mut 0),
+            Ok(h256(
+                "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+            ))
+        );
+
+        let domain_separator = hash(h256(
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f",
+        ));
+        let message = typed_message(domain_separator, mail);
+        assert_eq!(
+            build_typed_message_hash(&message.as_reader()),
+            Ok(h256(
+                "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+            ))
+        );
+    }
+
+    fn ref_cell(source: u64, index: u32, keccak: bool) -> Hash {
+        let ref_cell = RefCell::new_builder()
+            .source(Uint64::new_unchecked(source.to_le_bytes().to_vec().into()))
+            .index(Uint32::new_unchecked(index.to_le_bytes().to_vec().into()))
+            .keccak(Bool::new_unchecked(vec![keccak as u8].into()))
+            .build();
+        Hash::new_builder().set(ref_cell).build()
+    }
+
+    #[test]
+    fn test_ref_cell_group_input() {
+        MockTransaction {
+            input_count: 3,
+            group_inputs: vec![2],
+            input_cell_data: vec![vec![1; 32], vec![2; 32], vec![3; 32]],
+            ..Default::default()
+        }
+        .install();
+        let group_input = Source::GroupInput as u64;
+
+        // Index 0 of the group is global input 2, not global input 0
+        assert_eq!(
+            fetch_hash(&ref_cell(group_input, 0, false).as_reader()),
+            Ok([3; 32])
+        );
+        assert_eq!(
+            fetch_hash(&ref_cell(group_input, 0, true).as_reader()),
+            Ok(Keccak256::digest([3; 32]).into())
+        );
+        assert_eq!(
+            fetch_hash(&ref_cell(group_input, 1, false).as_reader()),
+            Err(Error::RefCellIndexOutOfBound)
+        );
+        assert_eq!(
+            fetch_hash(&ref_cell(Source::Input as u64, 0, false).as_reader()),
+            Ok([1; 32])
+        );
+    }
+
+    #[test]
+    fn test_invalid_source() {
+        assert_eq!(u64_to_source(0), Err(Error::InvalidSource(0)));
+        assert_eq!(u64_to_source(u64::MAX), Err(Error::InvalidSource(u64::MAX)));
+
+        MockTransaction::default().install();
+        for source in [0, u64::MAX] {
+            assert_eq!(
+                fetch_hash(&ref_cell(source, 0, false).as_reader()),
+                Err(Error::InvalidSource(source))
+            );
+            assert_eq!(
+                fetch_hash(&ref_cell(source, 0, true).as_reader()),
+                Err(Error::InvalidSource(source))
+            );
+        }
+    }
+
+    fn ref_transaction(offset: u32, length: u32) -> Hash {
+        let ref_tx = RefTransaction::new_builder()
+            .offset(Uint32::new_unchecked(offset.to_le_bytes().to_vec().into()))
+            .length(Uint32::new_unchecked(length.to_le_bytes().to_vec().into()))
+            .build();
+        Hash::new_builder().set(ref_tx).build()
+    }
+
+    #[test]
+    fn test_ref_out_of_bound() {
+        MockTransaction {
+            input_count: 1,
+            input_cell_data: vec![vec![1; 32]],
+            transaction: vec![2; 64],
+            ..Default::default()
+        }
+        .install();
+        let build = |domain_separator: Hash, type_hash: Hash| {
+            let message = typed_message(domain_separator, struct_of(type_hash, &[]));
+            build_typed_message_hash(&message.as_reader())
+        };
+        let input = Source::Input as u64;
+
+        assert!(build(ref_cell(input, 0, false), ref_transaction(0, 32)).is_ok());
+        assert_eq!(
+            build(ref_cell(input, 1, false), ref_transaction(0, 32)),
+            Err(Error::RefCellIndexOutOfBound)
+        );
+        assert_eq!(
+            build(ref_cell(input, 0, false), ref_cell(input, 1, true)),
+            Err(Error::RefCellIndexOutOfBound)
+        );
+        assert_eq!(
+            build(ref_cell(input, 0, false), ref_transaction(64, 32)),
+            Err(Error::RefTransactionOutOfBound)
+        );
+        assert_eq!(
+            build(ref_transaction(1000, 32), hash([0; 32])),
+            Err(Error::RefTransactionOutOfBound)
+        );
+    }
+
+    #[test]
+    fn test_ref_cell_keccak() {
+        let data = (0..3000).map(|i| i as u8).collect::<Vec<_>>();
+        MockTransaction {
+            input_count: 1,
+            input_cell_data: vec![data.clone()],
+            ..Default::default()
+        }
+        .install();
+        let ref_cell = |offset: u32, keccak: bool| {
+            let ref_cell = RefCell::new_builder()
+                .source(Uint64::new_unchecked(
+                    (Source::Input as u64).to_le_bytes().to_vec().into(),
+                ))
+                .offset(Uint32::new_unchecked(offset.to_le_bytes().to_vec().into()))
+                .keccak(Bool::new_unchecked(vec![keccak as u8].into()))
+                .build();
+            Hash::new_builder().set(ref_cell).build()
+        };
+
+        let literal = fetch_hash(&ref_cell(5, false).as_reader()).unwrap();
+        let hashed = fetch_hash(&ref_cell(5, true).as_reader()).unwrap();
+        assert_eq!(literal, data[5..37]);
+        assert_eq!(hashed, Keccak256::digest(&data[5..]).as_slice());
+        assert_ne!(literal, hashed);
+
+        assert_eq!(
+            fetch_hash(&ref_cell(3000, true).as_reader()),
+            Ok(Keccak256::digest([]).into())
+        );
+        assert_eq!(
+            fetch_hash(&ref_cell(3001, true).as_reader()),
+            Err(Error::CellDataEof)
+        );
+    }
+
+    fn ref_bytes(source: u64, length: u32) -> Value {
+        let ref_bytes = RefBytes::new_builder()
+            .source(Uint64::new_unchecked(source.to_le_bytes().to_vec().into()))
+            .length(Uint32::new_unchecked(length.to_le_bytes().to_vec().into()))
+            .build();
+        Value::new_builder().set(ref_bytes).build()
+    }
+
+    #[test]
+    fn test_restricted_message() {
+        let outputs_data = BytesVec::new_builder().push([4u8; 32][..].pack()).build();
+        MockTransaction {
+            input_count: 1,
+            input_cell_data: vec![vec![1; 32]],
+            transaction: Transaction::new_builder()
+                .raw(
+                    RawTransaction::new_builder()
+                        .outputs_data(outputs_data)
+                        .build(),
+                )
+                .build()
+                .as_slice()
+                .to_vec(),
+            ..Default::default()
+        }
+        .install();
+        let input = Source::Input as u64;
+        let output = Source::Output as u64;
+        let message = |domain_separator: Hash, value: Value| {
+            let inner = struct_of(hash([0; 32]), &[value]);
+            let inner = Value::new_builder().set(inner).build();
+            typed_message(domain_separator, struct_of(hash([0; 32]), &[inner]))
+        };
+        let build = |message: &TypedMessage| {
+            (
+                build_typed_message_hash(&message.as_reader()),
+                build_typed_message_hash_restricted(&message.as_reader()),
+            )
+        };
+
+        let (unrestricted, restricted) =
+            build(&message(ref_cell(input, 0, false), ref_bytes(input, 32)));
+        assert!(unrestricted.is_ok());
+        assert_eq!(restricted, unrestricted);
+
+        for message in [
+            message(ref_cell(output, 0, false), ref_bytes(input, 32)),
+            message(ref_cell(input, 0, false), ref_bytes(output, 32)),
+            message(ref_cell(input, 0, false), ref_bytes(output, 0)),
+        ] {
+            let (unrestricted, restricted) = build(&message);
+            assert!(unrestricted.is_ok());
+            assert_eq!(restricted, Err(Error::OutputRefCell));
+        }
+    }
+
+    #[test]
+    fn test_ref_bytes_matches_inline_bytes() {
+        let data = (0..2500).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+        MockTransaction {
+            input_count: 1,
+            input_cell_data: vec![data.clone()],
+            ..Default::default()
+        }
+        .install();
+        let inline = |data: &[u8]| {
+            Value::new_builder()
+                .set(Bytes::new_builder().set(bytes(data)).build())
+                .build()
+        };
+        let referenced = |offset: u32, length: u32| {
+            let ref_bytes = RefBytes::new_builder()
+                .source(Uint64::new_unchecked(
+                    (Source::Input as u64).to_le_bytes().to_vec().into(),
+                ))
+                .offset(Uint32::new_unchecked(offset.to_le_bytes().to_vec().into()))
+                .length(Uint32::new_unchecked(length.to_le_bytes().to_vec().into()))
+                .build();
+            Value::new_builder().set(ref_bytes).build()
+        };
+
+        assert_eq!(encode(&referenced(0, 2500)), encode(&inline(&data)));
+        assert_eq!(
+            encode(&referenced(10, 100)),
+            encode(&inline(&data[10..110]))
+        );
+        assert_eq!(encode(&referenced(2500, 0)), encode(&inline(&[])));
+        assert_eq!(encode(&referenced(10, 2500)), Err(Error::CellDataEof));
+    }
+
+    #[test]
+    fn test_ref_script() {
+        let script = Script::new_builder().args([1u8; 20][..].pack()).build();
+        MockTransaction {
+            script: script.as_slice().to_vec(),
+            ..Default::default()
+        }
+        .install();
+        let ref_script = Hash::new_builder().set(RefScript::default()).build();
+
+        assert_eq!(
+            fetch_hash(&ref_script.as_reader()),
+            Ok(script.calc_script_hash().unpack())
+        );
+        let message = typed_message(ref_script, struct_of(hash([0; 32]), &[]));
+        let expected = typed_message(
+            hash(script.calc_script_hash().unpack()),
+            struct_of(hash([0; 32]), &[]),
+        );
+        assert_eq!(
+            build_typed_message_hash(&message.as_reader()),
+            build_typed_message_hash(&expected.as_reader())
+        );
+    }
+
+    #[test]
+    fn test_strict_numbers() {
+        let message =
+            |value: Value| typed_message(hash([0; 32]), struct_of(hash([1; 32]), &[value]));
+        let path = FieldPath {
+            struct_index: 0,
+            value_index: 0,
+        };
+        let build = |value: Value| {
+            let message = message(value);
+            (
+                build_typed_message_hash(&message.as_reader()),
+                build_typed_message_hash_strict(&message.as_reader()),
+            )
+        };
+
+        for value in [uint(&[0x00, 0x01]), int(&[0x00, 0x01]), int(&[0xff, 0x80])] {
+            let (loose, strict) = build(value);
+            assert!(loose.is_ok());
+            assert_eq!(strict, Err(Error::InvalidNumber(path)));
+        }
+        // Same numbers in minimal encodings hash the same in both modes
+        for value in [
+            uint(&[0x01]),
+            uint(&[0x00]),
+            int(&[0x01]),
+            int(&[0x80]),
+            int(&[0x00, 0x80]),
+            int(&[0xff, 0x7f]),
+        ] {
+            let (loose, strict) = build(value);
+            assert!(strict.is_ok());
+            assert_eq!(strict, loose);
+        }
+        assert_eq!(build(uint(&[0x00, 0x01])).0, build(uint(&[0x01])).0);
+    }
+
+    #[test]
+    fn test_message_field_count() {
+        for count in [0, 1, 3] {
+            let values = vec![uint(&[1]); count];
+            let message = typed_message(hash([0; 32]), struct_of(hash([1; 32]), &values));
+            assert_eq!(message_field_count(&message.as_reader()), Ok(count));
+        }
+        // Nested values are not counted
+        let inner = Value::new_builder()
+            .set(struct_of(hash([1; 32]), &[uint(&[1]), uint(&[2])]))
+            .build();
+        let message = typed_message(hash([0; 32]), struct_of(hash([1; 32]), &[inner]));
+        assert_eq!(message_field_count(&message.as_reader()), Ok(1));
+    }
+
+    #[test]
+    fn test_ref_transaction_length() {
+        let transaction = (0..64).collect::<Vec<u8>>();
+        MockTransaction {
+            transaction: transaction.clone(),
+            ..Default::default()
+        }
+        .install();
+
+        let mut expected = [0u8; 32];
+        expected[12..].copy_from_slice(&transaction[40..60]);
+        assert_eq!(
+            fetch_hash(&ref_transaction(40, 20).as_reader()),
+            Ok(expected)
+        );
+        assert_eq!(
+            fetch_hash(&ref_transaction(32, 32).as_reader()),
+            Ok(transaction[32..].try_into().unwrap())
+        );
+        assert_eq!(
+            fetch_hash(&ref_transaction(50, 20).as_reader()),
+            Err(Error::CellDataEof)
+        );
+        assert_eq!(
+            fetch_hash(&ref_transaction(0, 0).as_reader()),
+            Err(Error::InvalidRefLength)
+        );
+        assert_eq!(
+            fetch_hash(&ref_transaction(0, 33).as_reader()),
+            Err(Error::InvalidRefLength)
+        );
+    }
+
+    #[test]
+    fn test_strict_string_utf8() {
+        let message =
+            |value: Value| typed_message(hash([0; 32]), struct_of(hash([1; 32]), &[value]));
+
+        let valid = message(string("héllo, 世界"));
+        assert!(build_typed_message_hash_strict(&valid.as_reader()).is_ok());
+        assert_eq!(
+            build_typed_message_hash_strict(&valid.as_reader()),
+            build_typed_message_hash(&valid.as_reader())
+        );
+
+        let invalid = message(
+            Value::new_builder()
+                .set(
+                    MolString::new_builder()
+                        .set(bytes(&[0x61, 0xc3, 0x28]))
+                        .build(),
+                )
+                .build(),
+        );
+        assert!(build_typed_message_hash(&invalid.as_reader()).is_ok());
+        assert_eq!(
+            build_typed_message_hash_strict(&invalid.as_reader()),
+            Err(Error::InvalidUtf8)
+        );
+    }
+
+    #[test]
+    fn test_ref_transaction_keccak() {
+        let transaction = (0..3000).map(|i| i as u8).collect::<Vec<_>>();
+        MockTransaction {
+            transaction: transaction.clone(),
+            ..Default::default()
+        }
+        .install();
+        let ref_transaction = |offset: u32, length: u32| {
+            let ref_tx = RefTransaction::new_builder()
+                .offset(Uint32::new_unchecked(offset.to_le_bytes().to_vec().into()))
+                .length(Uint32::new_unchecked(length.to_le_bytes().to_vec().into()))
+                .keccak(Bool::new_unchecked(vec![1].into()))
+                .build();
+            Hash::new_builder().set(ref_tx).build()
+        };
+        let fetch = |offset, length| fetch_hash(&ref_transaction(offset, length).as_reader());
+
+        // Spans several chunks
+        assert_eq!(
+            fetch(5, 2990),
+            Ok(Keccak256::digest(&transaction[5..2995]).into())
+        );
+        assert_eq!(fetch(0, 0), Ok(Keccak256::digest([]).into()));
+        // A huge length is rejected once the transaction ends, without
+        // allocating a buffer of that size
+        assert_eq!(fetch(0, u32::MAX), Err(Error::CellDataEof));
+        assert_eq!(fetch(2000, 1001), Err(Error::CellDataEof));
+        assert_eq!(fetch(3000, u32::MAX), Err(Error::RefTransactionOutOfBound));
+    }
+
+    #[test]
+    fn test_value_as_number_wrong_type() {
+        let bytes_value = Value::new_builder()
+            .set(Bytes::new_builder().set(bytes(&[1])).build())
+            .build();
+        assert_eq!(
+            value_as_u128(&bytes_value.as_reader()),
+            Err(Error::UnexpectedValueType)
+        );
+        assert_eq!(
+            value_as_i128(&bytes_value.as_reader()),
+            Err(Error::UnexpectedValueType)
+        );
+        // Signedness must match too
+        assert_eq!(
+            value_as_u128(&int(&[1]).as_reader()),
+            Err(Error::UnexpectedValueType)
+        );
+        assert_eq!(
+            value_as_i128(&uint(&[1]).as_reader()),
+            Err(Error::UnexpectedValueType)
+        );
+        // A malformed number of the right variant is still InvalidNumber
+        assert_eq!(
+            value_as_u128(&uint(&[]).as_reader()),
+            Err(Error::InvalidNumber(FieldPath::default()))
+        );
+    }
 }
@@ -0,0 +1,73 @@
+//! Fuzzing entry point for cargo-fuzz. Arbitrary bytes are treated as the
+//! only witness of a single input transaction, then run through the same
+//! parsing and hashing code an on-chain script uses, over the mocked
+//! syscalls of `mock-syscalls` feature. The mocked transaction carries no
+//! cell data, so RefCell & RefTransaction hashes yield errors.
+//!
+//! The cargo-fuzz target lives in `fuzz/`, run it with
+//! `cargo fuzz run parse_witness`. `fuzz/corpus/parse_witness` holds
+//! `seed_corpus` as files.
+
+use crate::{
+    eip712::build_typed_message_hash,
+    generate_sighash_all_hash,
+    schemas::{
+        basic::{Sighash, SighashWithAction},
+        top_level::{ExtendedWitness, ExtendedWitnessReader, ExtendedWitnessUnionReader},
+    },
+    MockTransaction,
+};
+use alloc::{vec, vec::Vec};
+use molecule::prelude::{Builder, Entity, Reader};
+
+/// Parses `data` as ExtendedWitness, and when it succeeds, generates
+/// sighash-all message hash of a transaction carrying `data` as its sole
+/// witness, as well as EIP-712 hash of the action message if any. Errors are
+/// expected and ignored, only panics are of interest.
+pub fn fuzz_parse_witness(data: &[u8]) {
+    let witness = match ExtendedWitnessReader::from_slice(data) {
+        Ok(w) => w,
+        Err(_) => return,
+    };
+    MockTransaction {
+        input_count: 1,
+        group_inputs: vec![0],
+        witnesses: vec![data.to_vec()],
+        ..Default::default()
+    }
+    .install();
+    if let ExtendedWitnessUnionReader::SighashWithAction(s) = witness.to_enum() {
+        let _ = build_typed_message_hash(&s.message());
+    }
+    let _ = generate_sighash_all_hash();
+}
+
+/// A couple of valid witnesses to seed the fuzzing corpus
+pub fn seed_corpus() -> Vec<Vec<u8>> {
+    vec![
+        ExtendedWitness::new_builder()
+            .set(Sighash::default())
+            .build()
+            .as_slice()
+            .to_vec(),
+        ExtendedWitness::new_builder()
+            .set(SighashWithAction::default())
+            .build()
+            .as_slice()
+            .to_vec(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_corpus() {
+        for seed in seed_corpus() {
+            for end in 0..=seed.len() {
+                fuzz_parse_witness(&seed[..end]);
+            }
+        }
+    }
+}
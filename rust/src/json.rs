@@ -0,0 +1,211 @@
+//! Converts molecule Value trees into JSON for debugging and tooling, making
+//! it easy to diff a signer's intent against the on-chain decoded message.
+
+use crate::{
+    eip712::read_bool,
+    schemas::basic::{
+        BoolReader, HashReader, HashUnionReader, SerializedValuesReader, ValueReader,
+        ValueUnionReader,
+    },
+};
+use molecule::prelude::Reader;
+use serde_json::{json, Value};
+use std::{format, string::String, vec::Vec};
+
+/// Converts a Value into a tagged JSON object, such as `{"uint": "0x01"}`.
+/// Structs and arrays are converted recursively. Since values nested in
+/// structs and arrays are kept in serialized form, one that fails to parse is
+/// kept as `{"invalid": "0x..."}`. Likewise, Bool values and keccak flags of
+/// referenced hashes holding anything other than 0 or 1 are kept as
+/// `{"invalid_bool": "0x..."}`, so malformed messages can still be dumped.
+pub fn value_to_json(value: &ValueReader) -> Value {
+    match value.to_enum() {
+        ValueUnionReader::Struct(s) => json!({
+            "struct": {
+                "type_hash": hash_to_json(&s.type_hash()),
+                "values": values_to_json(&s.values()),
+            }
+        }),
+        ValueUnionReader::Array(a) => json!({ "array": values_to_json(&a.values()) }),
+        ValueUnionReader::Bool(b) => json!({ "bool": bool_to_json(&b) }),
+        ValueUnionReader::Bytes(b) => json!({ "bytes": to_hex(b.raw_data()) }),
+        ValueUnionReader::String(s) => {
+            json!({ "string": String::from_utf8_lossy(s.raw_data()) })
+        }
+        ValueUnionReader::Address(a) => json!({ "address": to_hex(a.raw_data()) }),
+        ValueUnionReader::FixedBytes(f) => json!({ "fixed_bytes": to_hex(f.raw_data()) }),
+        ValueUnionReader::Int(i) => json!({ "int": to_hex(i.raw_data()) }),
+        ValueUnionReader::Uint(u) => json!({ "uint": to_hex(u.raw_data()) }),
+        ValueUnionReader::RefBytes(r) => json!({
+            "ref_bytes": {
+                "source": le_number(r.source().raw_data()),
+                "index": le_number(r.index().raw_data()),
+                "offset": le_number(r.offset().raw_data()),
+                "length": le_number(r.length().raw_data()),
+            }
+        }),
+    }
+}
+
+fn values_to_json(values: &SerializedValuesReader) -> Vec<Value> {
+    values
+        .iter()
+        .map(|v| match ValueReader::from_slice(v.raw_data()) {
+            Ok(value) => value_to_json(&value),
+            Err(_) => json!({ "invalid": to_hex(v.raw_data()) }),
+        })
+        .collect()
+}
+
+fn bool_to_json(b: &BoolReader) -> Value {
+    match read_bool(b) {
+        Some(b) => json!(b),
+        None => json!({ "invalid_bool": to_hex(b.raw_data()) }),
+    }
+}
+
+fn hash_to_json(hash: &HashReader) -> Value {
+    match hash.to_enum() {
+        HashUnionReader::Byte32(h) => json!({ "byte32": to_hex(h.raw_data()) }),
+        HashUnionReader::RefCell(r) => json!({
+            "ref_cell": {
+                "source": le_number(r.source().raw_data()),
+                "index": le_number(r.index().raw_data()),
+                "offset": le_number(r.offset().raw_data()),
+                "keccak": bool_to_json(&r.keccak()),
+            }
+        }),
+        HashUnionReader::RefTransaction(r) => json!({
+            "ref_transaction": {
+                "offset": le_number(r.offset().raw_data()),
+                "length": le_number(r.length().raw_data()),
+                "keccak": bool_to_json(&r.keccak()),
+            }
+        }),
+        HashUnionReader::RefScript(_) => json!({ "ref_script": {} }),
+    }
+}
+
+fn le_number(data: &[u8]) -> u64 {
+    data.iter().rev().fold(0, |acc, b| (acc << 8) | (*b as u64))
+}
+
+fn to_hex(data: &[u8]) -> String {
+    let mut result = String::from("0x");
+    for b in data {
+        result.push_str(&format!("{:02x}", b));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::basic::{
+        Bool, Byte32, Bytes, Hash, RefCell, SerializedValues, Struct, Uint, Value as MolValue,
+    };
+    use molecule::prelude::{Builder, Byte, Entity};
+    use std::vec;
+
+    fn bool_value(b: u8) -> MolValue {
+        MolValue::new_builder()
+            .set(Bool::new_unchecked(vec![b].into()))
+            .build()
+    }
+
+    fn struct_value(type_hash: Hash, values: &[MolValue]) -> MolValue {
+        let values = values
+            .iter()
+            .map(|v| {
+                Bytes::new_builder()
+                    .set(v.as_slice().iter().map(|b| Byte::new(*b)).collect())
+                    .build()
+            })
+            .collect();
+        let s = Struct::new_builder()
+            .type_hash(type_hash)
+            .values(SerializedValues::new_builder().set(values).build())
+            .build();
+        MolValue::new_builder().set(s).build()
+    }
+
+    fn byte32_hash() -> Hash {
+        Hash::new_builder()
+            .set(Byte32::new_unchecked(vec![0xab; 32].into()))
+            .build()
+    }
+
+    fn ref_cell_hash(keccak: u8) -> Hash {
+        let ref_cell = RefCell::new_builder()
+            .keccak(Bool::new_unchecked(vec![keccak].into()))
+            .build();
+        Hash::new_builder().set(ref_cell).build()
+    }
+
+    #[test]
+    fn test_render_values() {
+        let uint = MolValue::new_builder()
+            .set(Uint::new_builder().set(vec![Byte::new(1)]).build())
+            .build();
+        let value = struct_value(byte32_hash(), &[bool_value(1), bool_value(0), uint]);
+        assert_eq!(
+            value_to_json(&value.as_reader()),
+            json!({
+                "struct": {
+                    "type_hash": { "byte32": to_hex(&[0xab; 32]) },
+                    "values": [{ "bool": true }, { "bool": false }, { "uint": "0x01" }],
+                }
+            })
+        );
+
+        let value = struct_value(ref_cell_hash(1), &[]);
+        assert_eq!(
+            value_to_json(&value.as_reader()),
+            json!({
+                "struct": {
+                    "type_hash": {
+                        "ref_cell": { "source": 0, "index": 0, "offset": 0, "keccak": true }
+                    },
+                    "values": [],
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_bool() {
+        assert_eq!(
+            value_to_json(&bool_value(2).as_reader()),
+            json!({ "bool": { "invalid_bool": "0x02" } })
+        );
+
+        let inner = struct_value(byte32_hash(), &[bool_value(1), bool_value(0xff)]);
+        let value = struct_value(byte32_hash(), &[bool_value(0), inner]);
+        assert_eq!(
+            value_to_json(&value.as_reader()),
+            json!({
+                "struct": {
+                    "type_hash": { "byte32": to_hex(&[0xab; 32]) },
+                    "values": [
+                        { "bool": false },
+                        {
+                            "struct": {
+                                "type_hash": { "byte32": to_hex(&[0xab; 32]) },
+                                "values": [
+                                    { "bool": true },
+                                    { "bool": { "invalid_bool": "0xff" } },
+                                ],
+                            }
+                        },
+                    ],
+                }
+            })
+        );
+
+        let value = struct_value(ref_cell_hash(2), &[]);
+        assert_eq!(
+            value_to_json(&value.as_reader())["struct"]["type_hash"]["ref_cell"]["keccak"],
+            json!({ "invalid_bool": "0x02" })
+        );
+    }
+}
@@ -1,28 +1,235 @@
 #![no_std]
 
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "std")]
+pub mod address;
+#[cfg(feature = "std")]
+pub mod builder;
 pub mod eip712;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+#[cfg(feature = "std")]
+pub mod json;
+pub mod otx;
 pub mod schemas;
+#[cfg(feature = "secp256k1")]
+pub mod secp256k1;
+mod syscalls;
+#[cfg(feature = "webauthn")]
+pub mod webauthn;
+pub mod witness_cache;
+
+#[cfg(feature = "mock-syscalls")]
+pub use crate::syscalls::MockTransaction;
 
-use crate::schemas::{
-    basic::SighashWithAction,
-    top_level::{ExtendedWitnessReader, ExtendedWitnessUnionReader},
+use crate::{
+    eip712::{build_typed_message_hash, domain_separator_hash},
+    schemas::{
+        basic::{
+            SighashWithAction, TypedMessageReader, TypedMessageUnionReader, ValueReader,
+            ValueUnionReader,
+        },
+        top_level::{ExtendedWitnessReader, ExtendedWitnessUnion, ExtendedWitnessUnionReader},
+    },
+    syscalls::{
+        load_cell_lock_hash, load_full_transaction, load_input_since, load_tx_hash, load_witness,
+    },
+    witness_cache::{DirectLoader, WitnessCache, WitnessLoader},
 };
-use blake2b_ref::Blake2bBuilder;
+use alloc::vec::Vec;
+use blake2b_ref::{Blake2b, Blake2bBuilder};
 use ckb_std::{
     ckb_constants::Source,
+    ckb_types::packed::{OutPoint, WitnessArgsReader},
     error::SysError,
-    high_level::{load_input_since, load_tx_hash, load_witness},
 };
-use molecule::{error::VerificationError, prelude::Reader};
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    ops::RangeFrom,
+};
+use molecule::{
+    error::VerificationError,
+    prelude::{Entity, Reader},
+};
+
+/// Discriminator byte hashed right after tx hash in sighash-all message
+/// hash, when the first witness of current script group is
+/// SighashWithAction. It is followed by the molecule serialized message.
+/// The discriminator separates the two witness variants within the hash
+/// domain, so a Sighash witness can never produce the same preimage as a
+/// SighashWithAction one.
+pub const SIGHASH_WITH_ACTION_TAG: u8 = 1;
+/// Discriminator byte hashed right after tx hash in sighash-all message
+/// hash, when the first witness of current script group is Sighash.
+pub const SIGHASH_TAG: u8 = 0;
 
 #[derive(Eq, PartialEq, Debug, Clone, Copy)]
 pub enum Error {
     Sys(SysError),
-    DuplicateAction,
+    /// Witness indices of the first two SighashWithAction witnesses found
+    DuplicateAction {
+        first: usize,
+        second: usize,
+    },
     MoleculeEncoding,
     NotTypedTransaction,
     NotSighashVariant,
     NonEmptyGroupWitness,
+    NotOtxTransaction,
+    InvalidOtxIndex,
+    InvalidRecipientField,
+    RecipientNotFound,
+    ScanLimitExceeded,
+    TypedMessage(eip712::Error),
+    MixedDomains,
+    InvalidAddressField,
+    MissingGroupWitness,
+    InvalidSealLength,
+    InputCountOverflow,
+    SignatureVerification,
+    ScriptArgsLength,
+    UnexpectedTypedTransaction,
+    UnknownWitnessVariant,
+    EmptyAction,
+    /// Witness indices of the first two OtxStart witnesses found
+    DuplicateOtxStart {
+        first: usize,
+        second: usize,
+    },
+}
+
+impl Error {
+    /// Short static description of the error, usable in no_std scripts
+    /// without allocation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Error::Sys(_) => "syscall error",
+            Error::DuplicateAction { .. } => "duplicate action",
+            Error::MoleculeEncoding => "molecule encoding",
+            Error::NotTypedTransaction => "not typed transaction",
+            Error::NotSighashVariant => "not sighash variant",
+            Error::NonEmptyGroupWitness => "non empty group witness",
+            Error::NotOtxTransaction => "not otx transaction",
+            Error::InvalidOtxIndex => "invalid otx index",
+            Error::InvalidRecipientField => "invalid recipient field",
+            Error::RecipientNotFound => "recipient not found",
+            Error::ScanLimitExceeded => "scan limit exceeded",
+            Error::TypedMessage(_) => "typed message error",
+            Error::MixedDomains => "mixed domains",
+            Error::InvalidAddressField => "invalid address field",
+            Error::MissingGroupWitness => "missing group witness",
+            Error::InvalidSealLength => "invalid seal length",
+            Error::InputCountOverflow => "input count overflow",
+            Error::SignatureVerification => "signature verification",
+            Error::ScriptArgsLength => "script args length",
+            Error::UnexpectedTypedTransaction => "unexpected typed transaction",
+            Error::UnknownWitnessVariant => "unknown witness variant",
+            Error::EmptyAction => "empty action",
+            Error::DuplicateOtxStart { .. } => "duplicate otx start",
+        }
+    }
+
+    /// Stable exit code of the error, for scripts returning errors as i8.
+    /// Syscall errors keep the numbers used by CKB: 1 for IndexOutOfBound,
+    /// 2 for ItemMissing, 3 for LengthNotEnough and 4 for Encoding, other
+    /// syscall errors all map to 9. The remaining variants take codes
+    /// starting from 10, in the order they are declared. Codes of existing
+    /// variants never change, new variants only receive new codes.
+    pub fn code(&self) -> i8 {
+        match self {
+            Error::Sys(SysError::IndexOutOfBound) => 1,
+            Error::Sys(SysError::ItemMissing) => 2,
+            Error::Sys(SysError::LengthNotEnough(_)) => 3,
+            Error::Sys(SysError::Encoding) => 4,
+            Error::Sys(_) => 9,
+            Error::DuplicateAction { .. } => 10,
+            Error::MoleculeEncoding => 11,
+            Error::NotTypedTransaction => 12,
+            Error::NotSighashVariant => 13,
+            Error::NonEmptyGroupWitness => 14,
+            Error::NotOtxTransaction => 15,
+            Error::InvalidOtxIndex => 16,
+            Error::InvalidRecipientField => 17,
+            Error::RecipientNotFound => 18,
+            Error::ScanLimitExceeded => 19,
+            Error::TypedMessage(_) => 20,
+            Error::MixedDomains => 21,
+            Error::InvalidAddressField => 22,
+            Error::MissingGroupWitness => 23,
+            Error::InvalidSealLength => 24,
+            Error::InputCountOverflow => 25,
+            Error::SignatureVerification => 26,
+            Error::ScriptArgsLength => 27,
+            Error::UnexpectedTypedTransaction => 28,
+            Error::UnknownWitnessVariant => 29,
+            Error::EmptyAction => 30,
+            Error::DuplicateOtxStart { .. } => 31,
+        }
+    }
+
+    pub fn is_duplicate_action(&self) -> bool {
+        matches!(self, Error::DuplicateAction { .. })
+    }
+
+    // Key for ordering and hashing: exit code first, then the inner error
+    // or witness indices
+    fn sort_key(&self) -> (i8, (u8, u8, u64, u64)) {
+        let inner = match self {
+            Error::Sys(e) => {
+                let (k, v) = sys_error_key(e);
+                (0, k, v, 0)
+            }
+            Error::TypedMessage(e) => e.sort_key(),
+            Error::DuplicateAction { first, second }
+            | Error::DuplicateOtxStart { first, second } => (0, 0, *first as u64, *second as u64),
+            _ => (0, 0, 0, 0),
+        };
+        (self.code(), inner)
+    }
+}
+
+// SysError implements neither Ord nor Hash, this provides a key for both.
+// Each variant, along with its payload, maps to a distinct key. ckb-std's
+// ckb2023 feature is always enabled so the match below stays exhaustive.
+pub(crate) fn sys_error_key(e: &SysError) -> (u8, u64) {
+    match e {
+        SysError::IndexOutOfBound => (0, 0),
+        SysError::ItemMissing => (1, 0),
+        SysError::LengthNotEnough(n) => (2, *n as u64),
+        SysError::Encoding => (3, 0),
+        SysError::Unknown(n) => (4, *n),
+        SysError::SpawnExceededMaxContentLength => (5, 0),
+        SysError::SpawnWrongMemoryLimit => (6, 0),
+        SysError::SpawnExceededMaxPeakMemory => (7, 0),
+    }
+}
+
+impl PartialOrd for Error {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Error {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl Hash for Error {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sort_key().hash(state);
+    }
+}
+
+impl From<Error> for i8 {
+    fn from(e: Error) -> Self {
+        e.code()
+    }
 }
 
 impl From<SysError> for Error {
@@ -37,82 +244,1075 @@ impl From<VerificationError> for Error {
     }
 }
 
-pub fn fetch_sighash_with_action() -> Result<SighashWithAction, Error> {
-    let mut i = 0;
-    let mut result = None;
-    // Look for the first SighashWithAction witness
-    while result.is_none() {
-        match load_witness(i, Source::Input) {
-            Ok(witness) => {
-                if let Ok(r) = ExtendedWitnessReader::from_slice(&witness) {
-                    if let ExtendedWitnessUnionReader::SighashWithAction(s) = r.to_enum() {
-                        result = Some(s.to_entity());
+/// `MoleculeEncoding` and syscall errors map to their counterparts, all
+/// other EIP-712 errors are wrapped in `TypedMessage`. No information is
+/// lost in this direction.
+impl From<eip712::Error> for Error {
+    fn from(e: eip712::Error) -> Self {
+        match e {
+            eip712::Error::MoleculeEncoding => Error::MoleculeEncoding,
+            eip712::Error::Sys(e) => Error::Sys(e),
+            e => Error::TypedMessage(e),
+        }
+    }
+}
+
+/// Reverse of `From<eip712::Error>`, unwrapping `TypedMessage`. Errors only
+/// produced by sighash-all processing have no EIP-712 counterpart and are
+/// returned unchanged as `Err`.
+impl TryFrom<Error> for eip712::Error {
+    type Error = Error;
+
+    fn try_from(e: Error) -> Result<Self, Self::Error> {
+        match e {
+            Error::MoleculeEncoding => Ok(eip712::Error::MoleculeEncoding),
+            Error::Sys(e) => Ok(eip712::Error::Sys(e)),
+            Error::TypedMessage(e) => Ok(e),
+            e => Err(e),
+        }
+    }
+}
+
+/// Parses a raw witness as ExtendedWitness, returning the contained variant.
+///
+/// ```
+/// use ckb_typed_message_signing::{
+///     parse_extended_witness,
+///     schemas::{basic::Sighash, top_level::{ExtendedWitness, ExtendedWitnessUnionReader}},
+/// };
+/// use molecule::prelude::{Builder, Entity};
+///
+/// let witness = ExtendedWitness::new_builder().set(Sighash::default()).build();
+/// match parse_extended_witness(witness.as_slice()).unwrap() {
+///     ExtendedWitnessUnionReader::SighashWithAction(s) => println!("action: {}", s.message()),
+///     ExtendedWitnessUnionReader::Sighash(s) => println!("lock: {}", s.lock()),
+///     _ => println!("otx related witness"),
+/// }
+/// ```
+pub fn parse_extended_witness(witness: &[u8]) -> Result<ExtendedWitnessUnionReader<'_>, Error> {
+    Ok(ExtendedWitnessReader::from_slice(witness)?.to_enum())
+}
+
+/// Molecule union ids of ExtendedWitness variants
+#[derive(Eq, PartialEq, Debug, Clone, Copy, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum ExtendedWitnessTag {
+    SighashWithAction = 0xff000001,
+    Sighash = 0xff000002,
+    Otx = 0xff000003,
+    OtxStart = 0xff000004,
+}
+
+impl ExtendedWitnessTag {
+    pub fn from_u32(id: u32) -> Option<Self> {
+        match id {
+            0xff000001 => Some(ExtendedWitnessTag::SighashWithAction),
+            0xff000002 => Some(ExtendedWitnessTag::Sighash),
+            0xff000003 => Some(ExtendedWitnessTag::Otx),
+            0xff000004 => Some(ExtendedWitnessTag::OtxStart),
+            _ => None,
+        }
+    }
+
+    pub fn to_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+// The exhaustive match here fails to compile when the schema gains a new
+// variant, reminding to extend ExtendedWitnessTag as well.
+impl From<&ExtendedWitnessUnionReader<'_>> for ExtendedWitnessTag {
+    fn from(witness: &ExtendedWitnessUnionReader<'_>) -> Self {
+        let tag = match witness {
+            ExtendedWitnessUnionReader::SighashWithAction(_) => {
+                ExtendedWitnessTag::SighashWithAction
+            }
+            ExtendedWitnessUnionReader::Sighash(_) => ExtendedWitnessTag::Sighash,
+            ExtendedWitnessUnionReader::Otx(_) => ExtendedWitnessTag::Otx,
+            ExtendedWitnessUnionReader::OtxStart(_) => ExtendedWitnessTag::OtxStart,
+        };
+        debug_assert_eq!(tag.to_u32(), witness.item_id());
+        tag
+    }
+}
+
+/// Witness layouts recognized by `classify_witness`
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum WitnessKind {
+    Extended(ExtendedWitnessTag),
+    LegacyWitnessArgs,
+    Unknown,
+}
+
+/// Tells apart ExtendedWitness and legacy WitnessArgs witnesses, which might
+/// coexist in one transaction. ExtendedWitness parsing is attempted first.
+///
+/// In theory the same bytes could be valid in both formats. In practice
+/// this cannot happen: molecule tables like WitnessArgs start with their
+/// total size, while ExtendedWitness starts with a union id of at least
+/// 0xff000001, and no witness can be nearly 4GB in size.
+pub fn classify_witness(witness: &[u8]) -> WitnessKind {
+    if let Ok(r) = ExtendedWitnessReader::from_slice(witness) {
+        return WitnessKind::Extended((&r.to_enum()).into());
+    }
+    if WitnessArgsReader::from_slice(witness).is_ok() {
+        return WitnessKind::LegacyWitnessArgs;
+    }
+    WitnessKind::Unknown
+}
+
+/// Reads the molecule union id at the front of a witness, without validating
+/// the payload. This is a cheap way to reject witnesses of unwanted variants
+/// before fully parsing them, a matching tag says nothing about the validity
+/// of the remaining bytes. `None` is returned for witnesses shorter than 4
+/// bytes, or those with an unknown union id.
+pub fn peek_extended_witness_tag(witness: &[u8]) -> Option<ExtendedWitnessTag> {
+    let id = witness.get(..4)?;
+    ExtendedWitnessTag::from_u32(u32::from_le_bytes(id.try_into().unwrap()))
+}
+
+/// Strict counterpart of `parse_extended_witness`: a witness whose union id
+/// is not understood by this version of the crate, possibly a variant added
+/// by a later protocol upgrade, yields `UnknownWitnessVariant` instead of
+/// being lumped together with malformed witnesses. Scripts wanting to fail
+/// closed on such upgrades can use this on witnesses they are expected to
+/// understand, the lenient scans elsewhere are unaffected.
+pub fn require_known_extended_witness(witness: &[u8]) -> Result<ExtendedWitnessUnion, Error> {
+    if witness.len() < 4 {
+        return Err(Error::MoleculeEncoding);
+    }
+    if peek_extended_witness_tag(witness).is_none() {
+        return Err(Error::UnknownWitnessVariant);
+    }
+    Ok(ExtendedWitnessReader::from_slice(witness)?
+        .to_entity()
+        .to_enum())
+}
+
+/// Iterates over all witnesses in `source` that can be parsed as
+/// ExtendedWitness, yielding each witness index along with the parsed
+/// variant. Empty witnesses and those in other formats are skipped.
+pub fn extended_witnesses(source: Source) -> ExtendedWitnessIter<DirectLoader> {
+    ExtendedWitnessIter::new(source, DirectLoader, usize::MAX)
+}
+
+/// Same as `extended_witnesses`, but witnesses are loaded through `cache`.
+pub fn extended_witnesses_cached(
+    source: Source,
+    cache: &mut WitnessCache,
+) -> ExtendedWitnessIter<&mut WitnessCache> {
+    ExtendedWitnessIter::new(source, cache, usize::MAX)
+}
+
+pub struct ExtendedWitnessIter<L> {
+    index: usize,
+    // Witnesses at or beyond `end` are never loaded, reaching `end` while
+    // more witnesses exist yields `ScanLimitExceeded`
+    end: usize,
+    source: Source,
+    loader: L,
+    done: bool,
+}
+
+impl<L> ExtendedWitnessIter<L> {
+    fn new(source: Source, loader: L, end: usize) -> Self {
+        ExtendedWitnessIter {
+            index: 0,
+            end,
+            source,
+            loader,
+            done: false,
+        }
+    }
+}
+
+impl<L: WitnessLoader> Iterator for ExtendedWitnessIter<L> {
+    type Item = Result<(usize, ExtendedWitnessUnion), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let i = self.index;
+            if i >= self.end {
+                // Only the length of witness `end` is loaded, telling
+                // whether witnesses are exhausted right at the limit
+                self.done = true;
+                return match self.loader.load_witness_len(i, self.source) {
+                    Ok(_) => Some(Err(Error::ScanLimitExceeded)),
+                    Err(SysError::IndexOutOfBound) => None,
+                    Err(e) => Some(Err(e.into())),
+                };
+            }
+            self.index += 1;
+            match self.loader.load_witness(i, self.source) {
+                Ok(witness) => {
+                    if let Ok(r) = ExtendedWitnessReader::from_slice(&witness) {
+                        return Some(Ok((i, r.to_entity().to_enum())));
                     }
                 }
+                Err(SysError::IndexOutOfBound) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
             }
-            Err(SysError::IndexOutOfBound) => return Err(Error::NotTypedTransaction),
-            Err(e) => return Err(e.into()),
-        };
-        i += 1;
+        }
+        None
     }
-    let result = result.unwrap();
+}
+
+/// Fetches the only SighashWithAction witness in current transaction.
+///
+/// Duplicate detection scans witnesses via `Source::Input`, which covers the
+/// full witness list: CKB resolves `load_witness(i, Source::Input)` to the
+/// i-th witness of the transaction, including witnesses beyond the number of
+/// input cells. A `Source::GroupInput` witness is merely the witness at the
+/// global index of a group input cell, hence it is always visited by this
+/// scan as well. No SighashWithAction can hide from the check by grouping.
+pub fn fetch_sighash_with_action() -> Result<SighashWithAction, Error> {
+    fetch_sighash_with_action_from(Source::Input)
+}
+
+/// Same as `fetch_sighash_with_action`, but only witnesses in `source` are
+/// scanned, and duplicates are only detected within `source`. With
+/// `Source::GroupInput`, this resolves the action among witnesses of current
+/// script group: indices are group-relative, and a SighashWithAction outside
+/// the group is neither returned nor treated as a duplicate, unlike the
+/// global scan.
+pub fn fetch_sighash_with_action_from(source: Source) -> Result<SighashWithAction, Error> {
+    fetch_unique_sighash_with_action(usize::MAX, source, &mut DirectLoader)
+}
+
+/// Same as `fetch_sighash_with_action`, but loads at most `max_witnesses`
+/// witnesses, bounding the cycles a transaction with many junk witnesses can
+/// burn. The limit counts both the initial search and the duplicate detection
+/// pass, in other words, only witnesses with indices below `max_witnesses`
+/// are loaded. The witness at `max_witnesses` is merely probed for its
+/// existence, so a transaction with exactly `max_witnesses` witnesses is
+/// fully checked. `ScanLimitExceeded` is returned when more witnesses exist
+/// beyond the limit.
+pub fn fetch_sighash_with_action_bounded(max_witnesses: usize) -> Result<SighashWithAction, Error> {
+    fetch_unique_sighash_with_action(max_witnesses, Source::Input, &mut DirectLoader)
+}
+
+/// Same as `fetch_sighash_with_action`, but witnesses are loaded through
+/// `cache`, see `witness_cache` module for when this pays off.
+pub fn fetch_sighash_with_action_cached(
+    cache: &mut WitnessCache,
+) -> Result<SighashWithAction, Error> {
+    fetch_unique_sighash_with_action(usize::MAX, Source::Input, cache)
+}
+
+fn fetch_unique_sighash_with_action<L: WitnessLoader>(
+    max_witnesses: usize,
+    source: Source,
+    loader: &mut L,
+) -> Result<SighashWithAction, Error> {
+    let mut actions = sighash_with_actions(max_witnesses, source, loader);
+    // Look for the first SighashWithAction witness
+    let (i, result) = actions
+        .next()
+        .transpose()?
+        .ok_or(Error::NotTypedTransaction)?;
     // A single transaction must only have one SighashWithAction
+    if let Some((j, _)) = actions.next().transpose()? {
+        return Err(Error::DuplicateAction {
+            first: i,
+            second: j,
+        });
+    }
+    Ok(result)
+}
+
+/// Fetches the first SighashWithAction witness in current transaction,
+/// stopping right there without checking the remaining witnesses.
+///
+/// WARNING: this skips duplicate detection. A transaction might then carry
+/// a second SighashWithAction with a different message, and different
+/// scripts could end up honoring different actions. Only use this when the
+/// uniqueness of SighashWithAction is already enforced elsewhere in current
+/// transaction, e.g. via `assert_no_duplicate_actions`, otherwise stick to
+/// `fetch_sighash_with_action`.
+pub fn fetch_first_sighash_with_action() -> Result<SighashWithAction, Error> {
+    sighash_with_actions(usize::MAX, Source::Input, DirectLoader)
+        .next()
+        .transpose()?
+        .map(|(_, s)| s)
+        .ok_or(Error::NotTypedTransaction)
+}
+
+/// Counts SighashWithAction witnesses across all witnesses of current
+/// transaction, for reporting purposes. Unlike `fetch_sighash_with_action`,
+/// duplicates are not treated as errors here.
+pub fn count_sighash_with_action() -> Result<usize, Error> {
+    count_sighash_with_action_in(&mut DirectLoader)
+}
+
+/// Same as `count_sighash_with_action`, but witnesses are loaded through
+/// `cache`.
+pub fn count_sighash_with_action_cached(cache: &mut WitnessCache) -> Result<usize, Error> {
+    count_sighash_with_action_in(cache)
+}
+
+fn count_sighash_with_action_in<L: WitnessLoader>(loader: &mut L) -> Result<usize, Error> {
+    sighash_with_actions(usize::MAX, Source::Input, loader)
+        .try_fold(0, |count, item| item.map(|_| count + 1))
+}
+
+/// Collects all SighashWithAction witnesses along with their witness
+/// indices, for aggregated transactions where multiple parties each bring
+/// their own action. No duplicate check is performed.
+///
+/// To pick the action relevant to current script group, a script usually
+/// takes the first witness of its group via `fetch_sighash_with_action_from`
+/// with `Source::GroupInput`, or checks `group_has_action`: only the action
+/// in the group's own first witness is covered by its sighash-all message
+/// hash.
+pub fn fetch_all_sighash_with_action() -> Result<Vec<(usize, SighashWithAction)>, Error> {
+    fetch_all_sighash_with_action_in(&mut DirectLoader)
+}
+
+/// Same as `fetch_all_sighash_with_action`, but witnesses are loaded through
+/// `cache`.
+pub fn fetch_all_sighash_with_action_cached(
+    cache: &mut WitnessCache,
+) -> Result<Vec<(usize, SighashWithAction)>, Error> {
+    fetch_all_sighash_with_action_in(cache)
+}
+
+fn fetch_all_sighash_with_action_in<L: WitnessLoader>(
+    loader: &mut L,
+) -> Result<Vec<(usize, SighashWithAction)>, Error> {
+    sighash_with_actions(usize::MAX, Source::Input, loader).collect()
+}
+
+/// Checks that at most one SighashWithAction witness exists in `source`,
+/// without returning the witness itself.
+pub fn assert_no_duplicate_actions(source: Source) -> Result<(), Error> {
+    assert_no_duplicate_actions_in(source, &mut DirectLoader)
+}
+
+/// Same as `assert_no_duplicate_actions`, but witnesses are loaded through
+/// `cache`.
+pub fn assert_no_duplicate_actions_cached(
+    source: Source,
+    cache: &mut WitnessCache,
+) -> Result<(), Error> {
+    assert_no_duplicate_actions_in(source, cache)
+}
+
+fn assert_no_duplicate_actions_in<L: WitnessLoader>(
+    source: Source,
+    loader: &mut L,
+) -> Result<(), Error> {
+    let mut actions = sighash_with_actions(usize::MAX, source, loader);
+    if let Some((i, _)) = actions.next().transpose()? {
+        if let Some((j, _)) = actions.next().transpose()? {
+            return Err(Error::DuplicateAction {
+                first: i,
+                second: j,
+            });
+        }
+    }
+    Ok(())
+}
+
+// SighashWithAction witnesses in `source` along with their witness indices,
+// witnesses at or beyond `end` are never loaded.
+fn sighash_with_actions<L: WitnessLoader>(
+    end: usize,
+    source: Source,
+    loader: L,
+) -> impl Iterator<Item = Result<(usize, SighashWithAction), Error>> {
+    ExtendedWitnessIter::new(source, loader, end).filter_map(|item| match item {
+        Ok((i, ExtendedWitnessUnion::SighashWithAction(s))) => Some(Ok((i, s))),
+        Ok(_) => None,
+        Err(e) => Some(Err(e)),
+    })
+}
+
+/// Returns the seal of SighashWithAction, which is kept in its `lock` field
+/// and usually holds the signature. The format of the seal is entirely
+/// defined by the lock script, this crate never interprets it.
+pub fn action_seal(sighash: &SighashWithAction) -> &[u8] {
+    sighash.as_reader().lock().raw_data()
+}
+
+/// Returns the seal of SighashWithAction as a fixed size array, such as a
+/// 65-byte secp256k1 signature. `InvalidSealLength` is returned when the seal
+/// is not exactly `N` bytes.
+pub fn action_seal_as_array<const N: usize>(sighash: &SighashWithAction) -> Result<[u8; N], Error> {
+    action_seal(sighash)
+        .try_into()
+        .map_err(|_| Error::InvalidSealLength)
+}
+
+/// Returns the molecule serialized TypedMessage of SighashWithAction. These
+/// are exactly the bytes hashed right after `SIGHASH_WITH_ACTION_TAG` in
+/// sighash-all message hash, allowing the commitment to be reproduced
+/// off-chain.
+pub fn action_message_bytes(sighash: &SighashWithAction) -> &[u8] {
+    sighash.as_reader().message().as_slice()
+}
+
+/// Ensures the recipient declared in the action message owns at least one
+/// output cell. The recipient is read from the value at `field_index` of the
+/// top-level message struct, which must be a 32-byte FixedBytes holding the
+/// lock script hash of the recipient.
+pub fn assert_action_recipient(
+    sighash: &SighashWithAction,
+    field_index: usize,
+) -> Result<(), Error> {
+    let message = sighash.message();
+    let TypedMessageUnionReader::EIP712(eip712) = message.as_reader().to_enum();
+    let serialized_value = eip712
+        .message()
+        .values()
+        .get(field_index)
+        .ok_or(Error::InvalidRecipientField)?;
+    let value = ValueReader::from_slice(serialized_value.raw_data())?;
+    let recipient: [u8; 32] = match value.to_enum() {
+        ValueUnionReader::FixedBytes(f) => f
+            .raw_data()
+            .try_into()
+            .map_err(|_| Error::InvalidRecipientField)?,
+        _ => return Err(Error::InvalidRecipientField),
+    };
+
+    let mut i = 0;
     loop {
-        match load_witness(i, Source::Input) {
-            Ok(witness) => {
-                if let Ok(r) = ExtendedWitnessReader::from_slice(&witness) {
-                    if let ExtendedWitnessUnionReader::SighashWithAction(_) = r.to_enum() {
-                        return Err(Error::DuplicateAction);
-                    }
+        match load_cell_lock_hash(i, Source::Output) {
+            Ok(lock_hash) => {
+                if ct_eq(&lock_hash, &recipient) {
+                    return Ok(());
                 }
             }
-            Err(SysError::IndexOutOfBound) => return Ok(result),
+            Err(SysError::IndexOutOfBound) => return Err(Error::RecipientNotFound),
             Err(e) => return Err(e.into()),
         }
+        i += 1;
     }
 }
 
+/// Ensures all typed messages in current transaction, including the one in
+/// SighashWithAction and those in Otx witnesses, resolve to the same EIP-712
+/// domain separator, preventing messages of different domains from being
+/// mixed in one transaction. The common domain separator is returned.
+pub fn assert_uniform_domain() -> Result<[u8; 32], Error> {
+    assert_uniform_domain_in(extended_witnesses(Source::Input))
+}
+
+/// Same as `assert_uniform_domain`, but witnesses are loaded through
+/// `cache`.
+pub fn assert_uniform_domain_cached(cache: &mut WitnessCache) -> Result<[u8; 32], Error> {
+    assert_uniform_domain_in(extended_witnesses_cached(Source::Input, cache))
+}
+
+fn assert_uniform_domain_in<L: WitnessLoader>(
+    witnesses: ExtendedWitnessIter<L>,
+) -> Result<[u8; 32], Error> {
+    let mut domain = None;
+    for item in witnesses {
+        let message = match item?.1 {
+            ExtendedWitnessUnion::SighashWithAction(s) => s.message(),
+            ExtendedWitnessUnion::Otx(o) => o.message(),
+            _ => continue,
+        };
+        let TypedMessageUnionReader::EIP712(eip712) = message.as_reader().to_enum();
+        let current = domain_separator_hash(&eip712)?;
+        match domain {
+            None => domain = Some(current),
+            Some(d) if !ct_eq(&d, &current) => return Err(Error::MixedDomains),
+            _ => (),
+        }
+    }
+    domain.ok_or(Error::NotTypedTransaction)
+}
+
 pub fn is_typed_transaction() -> bool {
     fetch_sighash_with_action().is_ok()
 }
 
+/// Tells whether the first witness of current script group is
+/// SighashWithAction(true) or Sighash(false), returning `NotSighashVariant`
+/// otherwise. Unlike `is_typed_transaction`, this only looks at current
+/// script group, loading a single witness.
+pub fn group_has_action() -> Result<bool, Error> {
+    let witness = load_first_group_witness()?;
+    match parse_extended_witness(&witness)? {
+        ExtendedWitnessUnionReader::SighashWithAction(_) => Ok(true),
+        ExtendedWitnessUnionReader::Sighash(_) => Ok(false),
+        _ => Err(Error::NotSighashVariant),
+    }
+}
+
+/// Variant of the first witness in current script group, telling apart
+/// SighashWithAction witnesses whose top-level message struct holds no
+/// values at all.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum SighashVariant {
+    Sighash,
+    SighashWithAction,
+    /// SighashWithAction carrying an empty message struct
+    EmptyAction,
+}
+
+/// Reports whether the first witness of current script group is Sighash,
+/// SighashWithAction, or a SighashWithAction with an empty message, returning
+/// `NotSighashVariant` for other witnesses. An empty action means nothing to
+/// users, but is not equivalent to Sighash: its sighash-all message hash
+/// still covers `SIGHASH_WITH_ACTION_TAG` and the serialized message, so it
+/// never equals the hash of a Sighash witness with the same transaction.
+pub fn normalize_empty_action() -> Result<SighashVariant, Error> {
+    let witness = load_first_group_witness()?;
+    match parse_extended_witness(&witness)? {
+        ExtendedWitnessUnionReader::SighashWithAction(s) => {
+            let TypedMessageUnionReader::EIP712(eip712) = s.message().to_enum();
+            if eip712.message().values().is_empty() {
+                Ok(SighashVariant::EmptyAction)
+            } else {
+                Ok(SighashVariant::SighashWithAction)
+            }
+        }
+        ExtendedWitnessUnionReader::Sighash(_) => Ok(SighashVariant::Sighash),
+        _ => Err(Error::NotSighashVariant),
+    }
+}
+
+/// Opt-in guard for protocols refusing empty actions: returns `EmptyAction`
+/// when `normalize_empty_action` reports one, succeeds for Sighash and
+/// non-empty SighashWithAction.
+pub fn ensure_no_empty_action() -> Result<(), Error> {
+    match normalize_empty_action()? {
+        SighashVariant::EmptyAction => Err(Error::EmptyAction),
+        _ => Ok(()),
+    }
+}
+
+/// Guard for scripts only accepting legacy transactions: succeeds when no
+/// SighashWithAction witness exists, returns `UnexpectedTypedTransaction`
+/// when one or more exist. Unlike negating `is_typed_transaction`, syscall
+/// errors are propagated instead of being taken as a legacy transaction.
+pub fn ensure_not_typed_transaction() -> Result<(), Error> {
+    match sighash_with_actions(usize::MAX, Source::Input, DirectLoader)
+        .next()
+        .transpose()?
+    {
+        Some(_) => Err(Error::UnexpectedTypedTransaction),
+        None => Ok(()),
+    }
+}
+
 /// Generates sighash-all message hash for typed transaction. For performance
 /// reason, this function requires the caller to ensure that current CKB
 /// transaction is a typed transaction
+///
+/// NOTE: a SighashWithAction with an empty message is hashed like any
+/// other SighashWithAction, yielding a different hash than Sighash even
+/// though users see no action in either case. See `normalize_empty_action`
+/// for detecting, and optionally rejecting, such witnesses.
 pub fn generate_sighash_all_hash() -> Result<[u8; 32], Error> {
-    let mut hasher = Blake2bBuilder::new(32)
-        .personal(b"ckb-default-hash")
-        .build();
-    hasher.update(&load_tx_hash()?);
+    let output = generate_sighash_all_hash_n::<32>()?;
+    #[cfg(feature = "debug")]
+    debug_hex("sighash-all message hash", &output);
 
-    // For the first witness, we will need to hash the action if available.
-    {
-        let witness = load_witness(0, Source::GroupInput)?;
-        let extended_witness = ExtendedWitnessReader::from_slice(&witness)?;
-        match extended_witness.to_enum() {
-            ExtendedWitnessUnionReader::SighashWithAction(s) => {
-                // This byte distinguishes SighashWithAction from Sighash
-                hasher.update(&[1u8]);
-                // Do we still need to hash the length of slice here? Since
-                // molecule already validates the structure of the bytes, maybe
-                // we can skip the length field?
-                hasher.update(s.message().as_slice());
-            }
-            ExtendedWitnessUnionReader::Sighash(_) => {
-                hasher.update(&[0u8]);
+    Ok(output)
+}
+
+/// Returns the hasher of `generate_sighash_all_hash` right before it is
+/// finalized, allowing a script to mix in application data after the
+/// standard preimage. The hasher is 32-byte blake2b personalized with
+/// `ckb-default-hash`, like all other CKB hashes. Finalizing it without
+/// further updates yields exactly sighash-all message hash.
+pub fn sighash_all_hasher() -> Result<Blake2b, Error> {
+    let mut hasher = new_blake2b();
+    for_each_preimage_part(|part| hash_preimage_part(&mut hasher, part))?;
+    Ok(hasher)
+}
+
+/// Off-chain twin of `generate_sighash_all_hash`, building the very same
+/// preimage from witnesses held in memory instead of syscalls.
+/// `group_witnesses` are witnesses of the script group in order, the first
+/// one must be Sighash or SighashWithAction while the rest must be empty.
+/// `extra_witnesses` are witnesses not belonging to any input cells.
+///
+/// ```
+/// # #[cfg(feature = "mock-syscalls")] {
+/// use ckb_typed_message_signing::{
+///     generate_sighash_all_hash, sighash_all_hash_from_parts,
+///     schemas::{basic::SighashWithAction, top_level::ExtendedWitness},
+///     MockTransaction,
+/// };
+/// use molecule::prelude::{Builder, Entity};
+///
+/// let witness = ExtendedWitness::new_builder()
+///     .set(SighashWithAction::default())
+///     .build();
+/// let witness = witness.as_slice();
+/// MockTransaction {
+///     tx_hash: [3u8; 32],
+///     input_count: 2,
+///     group_inputs: vec![0, 1],
+///     witnesses: vec![witness.to_vec(), vec![], vec![1, 2, 3]],
+///     ..Default::default()
+/// }
+/// .install();
+///
+/// assert_eq!(
+///     generate_sighash_all_hash().unwrap(),
+///     sighash_all_hash_from_parts([3u8; 32], &[witness, &[]], &[&[1, 2, 3]]).unwrap()
+/// );
+/// # }
+/// ```
+pub fn sighash_all_hash_from_parts(
+    tx_hash: [u8; 32],
+    group_witnesses: &[&[u8]],
+    extra_witnesses: &[&[u8]],
+) -> Result<[u8; 32], Error> {
+    let (first, rest) = group_witnesses
+        .split_first()
+        .ok_or(Error::MissingGroupWitness)?;
+    if rest.iter().any(|w| !w.is_empty()) {
+        return Err(Error::NonEmptyGroupWitness);
+    }
+
+    let mut hasher = new_blake2b();
+    hash_preimage_part(&mut hasher, PreimagePart::TxHash(&tx_hash));
+    for_each_action_part(first, &mut |part| hash_preimage_part(&mut hasher, part))?;
+    for witness in extra_witnesses {
+        hash_preimage_part(&mut hasher, PreimagePart::ExtraWitness(witness));
+    }
+
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+
+    Ok(output)
+}
+
+/// Off-chain twin of `generate_sighash_all_hash` for an arbitrary script
+/// group, so a transaction builder can compute the hash of every group up
+/// front. `group_input_indices` are the global indices of input cells
+/// forming the group, in order, and `witnesses` are all witnesses of the
+/// transaction. Like `Source::GroupInput` on chain, group witnesses stop at
+/// the first index beyond `witnesses`. `extra_witnesses` are witnesses not
+/// belonging to any input cells.
+///
+/// ```
+/// # #[cfg(feature = "mock-syscalls")] {
+/// use ckb_typed_message_signing::{
+///     generate_sighash_all_hash, sighash_all_hash_for_group,
+///     schemas::{basic::Sighash, top_level::ExtendedWitness},
+///     MockTransaction,
+/// };
+/// use molecule::prelude::{Builder, Entity};
+///
+/// let witness = ExtendedWitness::new_builder().set(Sighash::default()).build();
+/// let witness = witness.as_slice();
+/// MockTransaction {
+///     tx_hash: [5u8; 32],
+///     input_count: 3,
+///     group_inputs: vec![1, 2],
+///     witnesses: vec![vec![9], witness.to_vec(), vec![], vec![4, 5]],
+///     ..Default::default()
+/// }
+/// .install();
+///
+/// assert_eq!(
+///     generate_sighash_all_hash().unwrap(),
+///     sighash_all_hash_for_group(&[1, 2], &[&[9], witness, &[], &[4, 5]], &[&[4, 5]], [5u8; 32])
+///         .unwrap()
+/// );
+/// # }
+/// ```
+pub fn sighash_all_hash_for_group(
+    group_input_indices: &[usize],
+    witnesses: &[&[u8]],
+    extra_witnesses: &[&[u8]],
+    tx_hash: [u8; 32],
+) -> Result<[u8; 32], Error> {
+    let group_witnesses: Vec<&[u8]> = group_input_indices
+        .iter()
+        .map_while(|i| witnesses.get(*i).copied())
+        .collect();
+    sighash_all_hash_from_parts(tx_hash, &group_witnesses, extra_witnesses)
+}
+
+/// Same as `generate_sighash_all_hash`, but produces an `N`-byte blake2b
+/// digest. Note blake2b output length is part of its parameter block, so
+/// this is not a truncation of the 32-byte hash. `N` must be within 1 to 64
+/// bytes, which is checked at compile time.
+pub fn generate_sighash_all_hash_n<const N: usize>() -> Result<[u8; N], Error> {
+    const { assert!(N >= 1 && N <= 64, "blake2b output must be 1 to 64 bytes") };
+    let mut hasher = new_blake2b_n(N);
+    for_each_preimage_part(|part| hash_preimage_part(&mut hasher, part))?;
+
+    let mut output = [0u8; N];
+    hasher.finalize(&mut output);
+
+    Ok(output)
+}
+
+/// Layouts of the action message in sighash-all preimage, for interop with
+/// other cobuild implementations.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum HashVariant {
+    /// Action message hashed as is, used by `generate_sighash_all_hash`
+    #[default]
+    V1NoMessageLength,
+    /// Action message prefixed with its length(u64 in little endian), the
+    /// same way extra witnesses are hashed
+    V2MessageLength,
+}
+
+/// Generates an extended sighash-all message hash, which additionally
+/// commits to dependencies explicitly: after the standard preimage, the
+/// number of cell deps(u64 in little endian) and the out point of each cell
+/// dep are hashed, followed by the number of header deps(u64 in little
+/// endian) and each header dep hash. Tx hash already covers all deps, this
+/// is merely belt and suspenders.
+///
+/// The result is a superset commitment, it never equals the value of
+/// `generate_sighash_all_hash`, signers must know which one to sign.
+pub fn generate_sighash_all_hash_with_deps() -> Result<[u8; 32], Error> {
+    let mut hasher = new_blake2b();
+    for_each_preimage_part(|part| hash_preimage_part(&mut hasher, part))?;
+
+    // There is no dedicated syscall for cell deps & header deps, they can
+    // only be read from the full transaction.
+    let raw = load_full_transaction()?.raw();
+    hasher.update(&(raw.cell_deps().len() as u64).to_le_bytes());
+    for cell_dep in raw.cell_deps().into_iter() {
+        hasher.update(cell_dep.out_point().as_slice());
+    }
+    hasher.update(&(raw.header_deps().len() as u64).to_le_bytes());
+    for header_dep in raw.header_deps().into_iter() {
+        hasher.update(header_dep.as_slice());
+    }
+
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+
+    Ok(output)
+}
+
+/// Generates sighash-all message hash using the specified preimage layout.
+/// With the default variant, this is identical to
+/// `generate_sighash_all_hash`.
+pub fn generate_sighash_all_hash_variant(variant: HashVariant) -> Result<[u8; 32], Error> {
+    let mut hasher = new_blake2b();
+    for_each_preimage_part(|part| match (variant, part) {
+        (HashVariant::V2MessageLength, PreimagePart::Message(m)) => {
+            hasher.update(&(m.len() as u64).to_le_bytes());
+            hasher.update(m);
+        }
+        (_, part) => hash_preimage_part(&mut hasher, part),
+    })?;
+
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+
+    Ok(output)
+}
+
+/// Network a signature is meant for, see
+/// `generate_sighash_all_hash_for_network`
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    /// Any other network, identified by its discriminator byte. Note 0 and
+    /// 1 are taken by `Mainnet` and `Testnet` respectively.
+    Custom(u8),
+}
+
+impl Network {
+    /// Discriminator byte hashed for this network
+    pub fn discriminator(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0,
+            Network::Testnet => 1,
+            Network::Custom(n) => *n,
+        }
+    }
+}
+
+/// Same as `generate_sighash_all_hash`, but the discriminator byte of
+/// `network` is hashed right after tx hash, so a signature made for one
+/// network never verifies on another. This changes the hash for every
+/// network, mainnet included: it is opt-in, deployments relying on
+/// `generate_sighash_all_hash` keep their current hashes.
+///
+/// ```
+/// # #[cfg(feature = "mock-syscalls")] {
+/// use ckb_typed_message_signing::{
+///     generate_sighash_all_hash_for_network,
+///     schemas::{basic::Sighash, top_level::ExtendedWitness},
+///     MockTransaction, Network,
+/// };
+/// use molecule::prelude::{Builder, Entity};
+///
+/// let witness = ExtendedWitness::new_builder().set(Sighash::default()).build();
+/// MockTransaction {
+///     tx_hash: [7u8; 32],
+///     input_count: 1,
+///     group_inputs: vec![0],
+///     witnesses: vec![witness.as_slice().to_vec()],
+///     ..Default::default()
+/// }
+/// .install();
+///
+/// let hex = |h: [u8; 32]| h.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+/// assert_eq!(
+///     hex(generate_sighash_all_hash_for_network(Network::Mainnet).unwrap()),
+///     "3703be1b1b27c67c5617c433b660f286e1bb3dc3ec18d8f6bf2765ea217c7dc6"
+/// );
+/// assert_eq!(
+///     hex(generate_sighash_all_hash_for_network(Network::Testnet).unwrap()),
+///     "10db4866aab23a72304e0a393a328467d8934d3a32582ca21ca882b51865919d"
+/// );
+/// assert_eq!(
+///     hex(generate_sighash_all_hash_for_network(Network::Custom(2)).unwrap()),
+///     "77ad9578d2233eb0f04a9c43872eceb425530801dbf98b330496af3976160336"
+/// );
+/// # }
+/// ```
+pub fn generate_sighash_all_hash_for_network(network: Network) -> Result<[u8; 32], Error> {
+    let mut hasher = new_blake2b();
+    for_each_preimage_part(|part| match part {
+        PreimagePart::TxHash(h) => {
+            hasher.update(h);
+            hasher.update(&[network.discriminator()]);
+        }
+        part => hash_preimage_part(&mut hasher, part),
+    })?;
+
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+
+    Ok(output)
+}
+
+/// Same as `generate_sighash_all_hash`, except that for SighashWithAction,
+/// the EIP-712 hash of the action message(see
+/// `eip712::build_typed_message_hash`) is hashed in place of the molecule
+/// serialized message. This binds sighash-all message hash to what an
+/// EIP-712 capable wallet displays. Errors in building EIP-712 hash, such as
+/// unresolvable referenced hashes, are returned as `TypedMessage`.
+pub fn generate_sighash_all_hash_eip712() -> Result<[u8; 32], Error> {
+    let mut hasher = new_blake2b();
+    let mut result = Ok(());
+    for_each_preimage_part(|part| match part {
+        PreimagePart::Message(m) => {
+            let eip712_hash = TypedMessageReader::from_slice(m)
+                .map_err(Error::from)
+                .and_then(|tm| build_typed_message_hash(&tm).map_err(Error::from));
+            match eip712_hash {
+                Ok(h) => hash_preimage_part(&mut hasher, PreimagePart::Message(&h)),
+                Err(e) => result = Err(e),
             }
-            _ => return Err(Error::NotSighashVariant),
         }
+        _ => hash_preimage_part(&mut hasher, part),
+    })?;
+    result?;
+
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+
+    Ok(output)
+}
+
+/// Generates a hash over tx hash, followed by the action tag and message in
+/// the first witness of current script group, hashed the same way as in
+/// sighash-all message hash. Other group witnesses and witnesses not
+/// belonging to any input cells are NOT covered.
+///
+/// This is meant for coordination within a script group only, it does NOT
+/// secure the full transaction: the uncovered witnesses can be altered
+/// without changing the hash. Never verify a signature against it alone,
+/// sighash-all message hash must be verified separately.
+pub fn generate_group_local_hash() -> Result<[u8; 32], Error> {
+    let mut hasher = new_blake2b();
+    hash_preimage_part(&mut hasher, PreimagePart::TxHash(&load_tx_hash()?));
+    for_each_action_part(&load_first_group_witness()?, &mut |part| {
+        hash_preimage_part(&mut hasher, part)
+    })?;
+
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+
+    Ok(output)
+}
+
+/// Common flow for lock scripts: fetches the action of current transaction,
+/// then generates sighash-all message hash, returning both so the caller has
+/// the action to inspect and the hash to verify signature against. It errors
+/// early with `NotTypedTransaction` for non-typed transactions, before any
+/// hashing work is done.
+///
+/// The two steps share little work: fetching scans all witnesses for the
+/// single SighashWithAction, while hashing only loads witnesses of current
+/// script group plus those beyond input cells. Also the action is not
+/// necessarily in current script group's first witness, which might be a
+/// plain Sighash, so the group witness must still be loaded for hashing.
+pub fn prepare_sighash_all() -> Result<(SighashWithAction, [u8; 32]), Error> {
+    let action = fetch_sighash_with_action()?;
+    let hash = generate_sighash_all_hash()?;
+    Ok((action, hash))
+}
+
+/// Generates a hash over only the witness part of sighash-all message: the
+/// action discriminant & message, then the remaining witnesses not belonging
+/// to any input cells, omitting tx hash. Comparing this against a peer's
+/// value helps isolating whether a sighash-all mismatch stems from tx hash or
+/// witnesses. This is for diagnostics only and must never be signed.
+pub fn witness_contribution_hash() -> Result<[u8; 32], Error> {
+    let mut hasher = new_blake2b();
+    for_each_preimage_part(|part| {
+        if !matches!(part, PreimagePart::TxHash(_)) {
+            hash_preimage_part(&mut hasher, part);
+        }
+    })?;
+
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+
+    Ok(output)
+}
+
+/// All inputs of sighash-all message hash, in the order they are hashed.
+/// Serializing this allows reproducing the exact hash input off-chain.
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct SighashTrace {
+    pub tx_hash: [u8; 32],
+    /// `SIGHASH_WITH_ACTION_TAG` or `SIGHASH_TAG`
+    pub action_tag: u8,
+    /// Molecule serialized action message, only present for SighashWithAction
+    pub message: Option<Vec<u8>>,
+    /// Witnesses not belonging to any input cells, each is hashed with its
+    /// length(u64 in little endian) prepended
+    pub extra_witnesses: Vec<Vec<u8>>,
+}
+
+impl SighashTrace {
+    /// Re-hashes the trace, yielding sighash-all message hash
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = new_blake2b();
+        hash_preimage_part(&mut hasher, PreimagePart::TxHash(&self.tx_hash));
+        hash_preimage_part(&mut hasher, PreimagePart::ActionTag(self.action_tag));
+        if let Some(message) = &self.message {
+            hash_preimage_part(&mut hasher, PreimagePart::Message(message));
+        }
+        for witness in &self.extra_witnesses {
+            hash_preimage_part(&mut hasher, PreimagePart::ExtraWitness(witness));
+        }
+
+        let mut output = [0u8; 32];
+        hasher.finalize(&mut output);
+        output
     }
+}
+
+/// Records every input of `generate_sighash_all_hash` instead of hashing
+/// them, for debugging sighash mismatches. The trace is gathered in the very
+/// same witness walk `generate_sighash_all_hash` performs, issuing the same
+/// syscalls, so it mirrors exactly what is hashed. Provers needing the full
+/// sighash-all preimage can collect it here in one pass, rather than
+/// fetching the action and reloading witnesses separately.
+pub fn sighash_computation_trace() -> Result<SighashTrace, Error> {
+    let mut trace = SighashTrace::default();
+    for_each_preimage_part(|part| match part {
+        PreimagePart::TxHash(h) => trace.tx_hash = *h,
+        PreimagePart::ActionTag(t) => trace.action_tag = t,
+        PreimagePart::Message(m) => trace.message = Some(m.to_vec()),
+        PreimagePart::ExtraWitness(w) => trace.extra_witnesses.push(w.to_vec()),
+    })?;
+    Ok(trace)
+}
+
+/// A single component of sighash-all preimage, as fed to Blake2b
+#[cfg(feature = "std")]
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub enum PreimageComponent {
+    TxHash([u8; 32]),
+    /// `SIGHASH_WITH_ACTION_TAG` or `SIGHASH_TAG`
+    ActionTag(u8),
+    /// Molecule serialized action message
+    Message(Vec<u8>),
+    /// A witness not belonging to any input cells, `len` is hashed as u64 in
+    /// little endian right before `bytes`
+    Witness {
+        len: u64,
+        bytes: Vec<u8>,
+    },
+}
+
+/// Lists each component `generate_sighash_all_hash` would hash, in order,
+/// without hashing. Comparing the list against another implementation's
+/// pinpoints the first component where the two diverge. Components are
+/// collected in the same witness walk used for hashing.
+#[cfg(feature = "std")]
+pub fn sighash_all_preimage_components() -> Result<Vec<PreimageComponent>, Error> {
+    let mut components = Vec::new();
+    for_each_preimage_part(|part| {
+        components.push(match part {
+            PreimagePart::TxHash(h) => PreimageComponent::TxHash(*h),
+            PreimagePart::ActionTag(t) => PreimageComponent::ActionTag(t),
+            PreimagePart::Message(m) => PreimageComponent::Message(m.to_vec()),
+            PreimagePart::ExtraWitness(w) => PreimageComponent::Witness {
+                len: w.len() as u64,
+                bytes: w.to_vec(),
+            },
+        })
+    })?;
+    Ok(components)
+}
+
+// Components of sighash-all preimage
+enum PreimagePart<'a> {
+    TxHash(&'a [u8; 32]),
+    ActionTag(u8),
+    Message(&'a [u8]),
+    ExtraWitness(&'a [u8]),
+}
+
+fn hash_preimage_part(hasher: &mut Blake2b, part: PreimagePart) {
+    match part {
+        PreimagePart::TxHash(h) => hasher.update(h),
+        PreimagePart::ActionTag(t) => hasher.update(&[t]),
+        PreimagePart::Message(m) => hasher.update(m),
+        PreimagePart::ExtraWitness(w) => {
+            hasher.update(&(w.len() as u64).to_le_bytes());
+            hasher.update(w);
+        }
+    }
+}
+
+// Number of bytes `hash_preimage_part` feeds to the hasher
+fn preimage_part_len(part: &PreimagePart) -> u64 {
+    match part {
+        PreimagePart::TxHash(h) => h.len() as u64,
+        PreimagePart::ActionTag(_) => 1,
+        PreimagePart::Message(m) => m.len() as u64,
+        PreimagePart::ExtraWitness(w) => 8 + w.len() as u64,
+    }
+}
+
+// Walks through sighash-all preimage, feeding each component to `f` in the
+// order they shall be hashed.
+fn for_each_preimage_part<F: FnMut(PreimagePart)>(mut f: F) -> Result<(), Error> {
+    f(PreimagePart::TxHash(&load_tx_hash()?));
+
+    // For the first witness, we will need to hash the action if available.
+    for_each_action_part(&load_first_group_witness()?, &mut f)?;
     // For the subsequent witnesses, we will ensure that they are empty
+    ensure_trailing_group_witnesses_empty()?;
+
+    // Hash remaining witnesses that do not belong to any input cells
     {
-        let mut i = 1;
+        let mut i = extra_witness_range()?.start;
         loop {
-            match load_witness(i, Source::GroupInput) {
+            match load_witness(i, Source::Input) {
                 Ok(w) => {
-                    if w.len() > 0 {
-                        return Err(Error::NonEmptyGroupWitness);
-                    }
+                    f(PreimagePart::ExtraWitness(&w));
                 }
                 Err(SysError::IndexOutOfBound) => {
                     break;
@@ -123,44 +1323,239 @@ pub fn generate_sighash_all_hash() -> Result<[u8; 32], Error> {
         }
     }
 
-    // Hash remaining witnesses that do not belong to any input cells
-    {
-        let mut i = calculate_inputs_len()?;
-        loop {
-            match load_witness(i, Source::Input) {
-                Ok(w) => {
-                    hasher.update(&(w.len() as u64).to_le_bytes());
-                    hasher.update(&w);
-                }
-                Err(SysError::IndexOutOfBound) => {
-                    break;
+    Ok(())
+}
+
+/// Ensures all witnesses of current script group, except for the first one
+/// holding Sighash / SighashWithAction, are empty. This is the check run by
+/// `generate_sighash_all_hash`, exposed so witness structure can be
+/// validated before any hashing work.
+pub fn ensure_trailing_group_witnesses_empty() -> Result<(), Error> {
+    let mut i = 1;
+    loop {
+        match load_witness(i, Source::GroupInput) {
+            Ok(w) => {
+                if !w.is_empty() {
+                    return Err(Error::NonEmptyGroupWitness);
                 }
-                Err(e) => return Err(e.into()),
             }
-            i += 1;
+            Err(SysError::IndexOutOfBound) => {
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        }
+        i += 1;
+    }
+}
+
+// Feeds the action tag, and the message for SighashWithAction, kept in
+// `witness` to `f`. `witness` is the first witness of current script group.
+fn for_each_action_part<F: FnMut(PreimagePart)>(witness: &[u8], f: &mut F) -> Result<(), Error> {
+    match parse_extended_witness(witness)? {
+        ExtendedWitnessUnionReader::SighashWithAction(s) => {
+            f(PreimagePart::ActionTag(SIGHASH_WITH_ACTION_TAG));
+            // Only the message field is hashed, never the seal kept in lock
+            // field, or the signature would have to sign itself. Each table
+            // field spans from its own offset to the next field's offset,
+            // and molecule verification(done by parse_extended_witness)
+            // ensures offsets never decrease, so the two fields can never
+            // overlap, no matter how the witness is crafted.
+            debug_assert!({
+                let lock = s.lock().as_slice().as_ptr_range();
+                let message = s.message().as_slice().as_ptr_range();
+                lock.end <= message.start || message.end <= lock.start
+            });
+            // Do we still need to hash the length of slice here? Since
+            // molecule already validates the structure of the bytes, maybe
+            // we can skip the length field?
+            f(PreimagePart::Message(s.message().as_slice()));
         }
+        ExtendedWitnessUnionReader::Sighash(_) => {
+            f(PreimagePart::ActionTag(SIGHASH_TAG));
+        }
+        _ => return Err(Error::NotSighashVariant),
+    }
+    Ok(())
+}
+
+/// Total number of bytes hashed by `generate_sighash_all_hash`, obtained by
+/// walking through the same preimage without hashing, useful for predicting
+/// hashing cost. Note the witnesses are still loaded.
+pub fn sighash_preimage_len() -> Result<u64, Error> {
+    let mut len = 0;
+    for_each_preimage_part(|part| len += preimage_part_len(&part))?;
+    Ok(len)
+}
+
+/// Indices of witnesses not bound to any input cells, which are hashed
+/// one by one in sighash-all message hash. Since the i-th witness belongs
+/// to the i-th input cell, these are the witnesses starting from the number
+/// of input cells.
+pub fn extra_witness_range() -> Result<RangeFrom<usize>, Error> {
+    Ok(extra_witness_start_index()?..)
+}
+
+/// Index of the first witness not bound to any input cells, i.e. where
+/// transaction builders can append their own witnesses. Each witness from
+/// here on is hashed in sighash-all message hash with its length(u64 in
+/// little endian) prepended.
+pub fn extra_witness_start_index() -> Result<usize, Error> {
+    calculate_inputs_len()
+}
+
+/// Compares two byte strings, such as hashes or signer identities, in
+/// constant time: all bytes are always visited, no matter where the first
+/// difference lies. Only the lengths, which are never secret, are compared
+/// up front. CKB scripts run deterministically, this is defense in depth for
+/// flows where timing could still leak, and is used wherever this crate
+/// compares hashes, signers or challenges.
+///
+/// ```
+/// use ckb_typed_message_signing::ct_eq;
+///
+/// let mut b = [7u8; 32];
+/// assert!(ct_eq(&[7u8; 32], &b));
+/// b[31] = 8;
+/// assert!(!ct_eq(&[7u8; 32], &b));
+/// assert!(!ct_eq(&[7u8; 32], &[7u8; 20]));
+/// ```
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    // Keep the compiler from short-circuiting the accumulation above
+    core::hint::black_box(diff) == 0
+}
+
+/// Writes `bytes` into `buf` as lowercase hex without allocation, returning
+/// the number of bytes written. `buf` should hold at least
+/// `bytes.len() * 2` bytes, otherwise only the leading bytes fitting in
+/// `buf` are written.
+///
+/// ```
+/// use ckb_typed_message_signing::write_hex;
+///
+/// let mut buf = [0u8; 64];
+/// let n = write_hex(&mut buf, &[0x01, 0xab, 0xff]);
+/// assert_eq!(&buf[..n], b"01abff");
+/// assert_eq!(write_hex(&mut buf[..3], &[0x01, 0xab]), 2);
+/// assert_eq!(&buf[..2], b"01");
+/// ```
+pub fn write_hex(buf: &mut [u8], bytes: &[u8]) -> usize {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut written = 0;
+    for (b, out) in bytes.iter().zip(buf.chunks_exact_mut(2)) {
+        out[0] = HEX[(b >> 4) as usize];
+        out[1] = HEX[(b & 0xf) as usize];
+        written += 2;
     }
+    written
+}
+
+// Prints `bytes` in hex via ckb-std's debug syscall, at most 32 bytes are
+// printed.
+#[cfg(feature = "debug")]
+pub(crate) fn debug_hex(label: &str, bytes: &[u8]) {
+    let mut buf = [0u8; 64];
+    let n = write_hex(&mut buf, bytes);
+    let hex = core::str::from_utf8(&buf[..n]).unwrap_or_default();
+    ckb_std::debug!("{}: {}", label, hex);
+}
+
+/// Same as `generate_sighash_all_hash`, but also returns the number of
+/// syscalls issued during the computation. Note that the count is taken at
+/// ckb-std's high level API: loading a witness larger than ckb-std's default
+/// buffer takes one more syscall internally, which is not reflected here.
+#[cfg(feature = "profile")]
+pub fn generate_sighash_all_hash_profiled() -> Result<([u8; 32], u64), Error> {
+    syscalls::reset_syscall_count();
+    let hash = generate_sighash_all_hash()?;
+    Ok((hash, syscalls::syscall_count()))
+}
 
+/// Generates a commitment to the exact set of cell deps used by current
+/// transaction. Out points of all cell deps are sorted by their molecule
+/// serialized bytes, then hashed together, so the commitment does not depend
+/// on the order cell deps are laid out. A lock can require this value to be
+/// included in the signed message to prevent dependency substitution.
+pub fn cell_deps_commitment() -> Result<[u8; 32], Error> {
+    let tx = load_full_transaction()?;
+    let mut out_points: Vec<OutPoint> = tx
+        .raw()
+        .cell_deps()
+        .into_iter()
+        .map(|cell_dep| cell_dep.out_point())
+        .collect();
+    out_points.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+
+    let mut hasher = new_blake2b();
+    for out_point in out_points {
+        hasher.update(out_point.as_slice());
+    }
     let mut output = [0u8; 32];
     hasher.finalize(&mut output);
 
     Ok(output)
 }
 
+// Reads lock field of the first witness in current script group, which
+// holds the signature for both Sighash and SighashWithAction.
+#[cfg(any(feature = "secp256k1", feature = "webauthn"))]
+pub(crate) fn load_group_lock() -> Result<Vec<u8>, Error> {
+    let witness = load_first_group_witness()?;
+    match parse_extended_witness(&witness)? {
+        ExtendedWitnessUnionReader::SighashWithAction(s) => Ok(s.lock().raw_data().to_vec()),
+        ExtendedWitnessUnionReader::Sighash(s) => Ok(s.lock().raw_data().to_vec()),
+        _ => Err(Error::NotSighashVariant),
+    }
+}
+
+// Loads the first witness of current script group, which holds the
+// Sighash / SighashWithAction structure.
+fn load_first_group_witness() -> Result<Vec<u8>, Error> {
+    match load_witness(0, Source::GroupInput) {
+        Ok(witness) => Ok(witness),
+        Err(SysError::IndexOutOfBound) => Err(Error::MissingGroupWitness),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub(crate) fn new_blake2b() -> Blake2b {
+    new_blake2b_n(32)
+}
+
+// Blake2b producing `n`-byte digests, personalized the same way as all
+// other CKB hashes
+fn new_blake2b_n(n: usize) -> Blake2b {
+    Blake2bBuilder::new(n).personal(b"ckb-default-hash").build()
+}
+
+// Upper bound of input cells probed by calculate_inputs_len. This is far
+// beyond what fits in a CKB block, a transaction claiming more inputs can
+// only come from a broken environment.
+const MAX_INPUTS_LEN: usize = 1 << 24;
+
 // Translated from https://github.com/nervosnetwork/ckb-system-scripts/blob/a7b7c75662ed950c9bd024e15f83ce702a54996e/c/common.h#L32-L66
-fn calculate_inputs_len() -> Result<usize, SysError> {
+fn calculate_inputs_len() -> Result<usize, Error> {
     let mut lo = 0;
     let mut hi = 4;
     loop {
         match load_input_since(hi, Source::Input) {
             Ok(_) => {
                 lo = hi;
-                hi *= 2;
+                hi = hi
+                    .checked_mul(2)
+                    .filter(|hi| *hi <= MAX_INPUTS_LEN)
+                    .ok_or(Error::InputCountOverflow)?;
             }
             Err(SysError::IndexOutOfBound) => {
                 break;
             }
-            Err(e) => return Err(e),
+            Err(e) => return Err(e.into()),
         }
     }
 
@@ -173,9 +1568,735 @@ fn calculate_inputs_len() -> Result<usize, SysError> {
             Err(SysError::IndexOutOfBound) => {
                 hi = i;
             }
-            Err(e) => return Err(e),
+            Err(e) => return Err(e.into()),
         }
     }
 
     Ok(hi)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::{
+        basic::{
+            Bytes, FixedBytes, Otx, OtxStart, SerializedValues, Sighash, Struct, TypedMessage,
+            Value, EIP712,
+        },
+        top_level::ExtendedWitness,
+    };
+    use alloc::vec;
+    use ckb_std::ckb_types::{
+        packed::{
+            Byte32, CellDep, CellDepVec, CellOutput, CellOutputVec, RawTransaction, Script,
+            Transaction,
+        },
+        prelude::*,
+    };
+    use molecule::prelude::{Builder, Byte};
+
+    fn sighash_witness() -> Vec<u8> {
+        ExtendedWitness::new_builder()
+            .set(Sighash::default())
+            .build()
+            .as_slice()
+            .to_vec()
+    }
+
+    fn out_point(n: u8) -> OutPoint {
+        OutPoint::new_builder()
+            .tx_hash(Byte32::new_unchecked(vec![n; 32].into()))
+            .build()
+    }
+
+    // Installs a single input transaction with a Sighash witness, whose
+    // cell deps point to `cell_deps` in order
+    fn install_with_deps(cell_deps: &[OutPoint], header_deps: &[Byte32]) {
+        let raw = RawTransaction::new_builder()
+            .cell_deps(
+                CellDepVec::new_builder()
+                    .set(
+                        cell_deps
+                            .iter()
+                            .map(|o| CellDep::new_builder().out_point(o.clone()).build())
+                            .collect(),
+                    )
+                    .build(),
+            )
+            .header_deps(header_deps.to_vec().pack())
+            .build();
+        MockTransaction {
+            tx_hash: [7u8; 32],
+            input_count: 1,
+            group_inputs: vec![0],
+            witnesses: vec![sighash_witness()],
+            transaction: Transaction::new_builder()
+                .raw(raw)
+                .build()
+                .as_slice()
+                .to_vec(),
+            ..Default::default()
+        }
+        .install();
+    }
+
+    #[test]
+    fn test_sighash_all_hash_with_deps() {
+        let header_dep = Byte32::new_unchecked(vec![9u8; 32].into());
+        install_with_deps(
+            &[out_point(1), out_point(2)],
+            core::slice::from_ref(&header_dep),
+        );
+
+        let mut hasher = new_blake2b();
+        hasher.update(&[7u8; 32]);
+        hasher.update(&[SIGHASH_TAG]);
+        hasher.update(&2u64.to_le_bytes());
+        hasher.update(out_point(1).as_slice());
+        hasher.update(out_point(2).as_slice());
+        hasher.update(&1u64.to_le_bytes());
+        hasher.update(header_dep.as_slice());
+        let mut expected = [0u8; 32];
+        hasher.finalize(&mut expected);
+
+        assert_eq!(generate_sighash_all_hash_with_deps(), Ok(expected));
+        assert_ne!(generate_sighash_all_hash(), Ok(expected));
+    }
+
+    #[test]
+    fn test_cell_deps_commitment() {
+        install_with_deps(&[out_point(1), out_point(2)], &[]);
+
+        let mut hasher = new_blake2b();
+        hasher.update(out_point(1).as_slice());
+        hasher.update(out_point(2).as_slice());
+        let mut expected = [0u8; 32];
+        hasher.finalize(&mut expected);
+
+        assert_eq!(cell_deps_commitment(), Ok(expected));
+    }
+
+    #[test]
+    fn test_cell_deps_commitment_ignores_order() {
+        install_with_deps(&[out_point(1), out_point(2), out_point(3)], &[]);
+        let commitment = cell_deps_commitment().unwrap();
+
+        install_with_deps(&[out_point(3), out_point(1), out_point(2)], &[]);
+        assert_eq!(cell_deps_commitment(), Ok(commitment));
+
+        install_with_deps(&[out_point(3), out_point(1)], &[]);
+        assert_ne!(cell_deps_commitment(), Ok(commitment));
+    }
+
+    fn action_witness() -> Vec<u8> {
+        ExtendedWitness::new_builder()
+            .set(SighashWithAction::default())
+            .build()
+            .as_slice()
+            .to_vec()
+    }
+
+    fn install_witnesses(witnesses: Vec<Vec<u8>>) {
+        MockTransaction {
+            input_count: 1,
+            group_inputs: vec![0],
+            witnesses,
+            ..Default::default()
+        }
+        .install();
+    }
+
+    #[test]
+    fn test_bounded_scan_at_limit() {
+        install_witnesses(vec![action_witness(), vec![]]);
+        assert_eq!(
+            fetch_sighash_with_action_bounded(2).map(|s| s.as_slice().to_vec()),
+            Ok(action_witness()[4..].to_vec())
+        );
+        assert_eq!(
+            fetch_sighash_with_action_bounded(1).map(|_| ()),
+            Err(Error::ScanLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn test_bounded_scan_duplicate_beyond_limit() {
+        install_witnesses(vec![action_witness(), vec![], action_witness()]);
+        assert_eq!(
+            fetch_sighash_with_action_bounded(2).map(|_| ()),
+            Err(Error::ScanLimitExceeded)
+        );
+        assert_eq!(
+            fetch_sighash_with_action_bounded(3).map(|_| ()),
+            Err(Error::DuplicateAction {
+                first: 0,
+                second: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_extended_witnesses_cached() {
+        install_witnesses(vec![action_witness(), vec![1], sighash_witness()]);
+        let indices = |witnesses: &mut dyn Iterator<Item = Result<_, Error>>| {
+            witnesses
+                .map(|item| item.map(|(i, _)| i))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let mut cache = WitnessCache::new();
+        assert_eq!(
+            indices(&mut extended_witnesses_cached(Source::Input, &mut cache)),
+            Ok(vec![0, 2])
+        );
+        // 3 witnesses plus the out of bound probe
+        assert_eq!(cache.len(), 4);
+        assert_eq!(
+            indices(&mut extended_witnesses(Source::Input)),
+            Ok(vec![0, 2])
+        );
+        assert_eq!(
+            assert_uniform_domain_cached(&mut cache),
+            assert_uniform_domain()
+        );
+        assert_eq!(cache.len(), 4);
+    }
+
+    #[test]
+    fn test_sys_error_ordering_matches_equality() {
+        let sys_errors = [
+            SysError::IndexOutOfBound,
+            SysError::ItemMissing,
+            SysError::LengthNotEnough(0),
+            SysError::LengthNotEnough(1),
+            SysError::Encoding,
+            SysError::SpawnExceededMaxContentLength,
+            SysError::SpawnWrongMemoryLimit,
+            SysError::SpawnExceededMaxPeakMemory,
+            SysError::Unknown(0),
+            SysError::Unknown(u64::MAX),
+        ];
+        for a in sys_errors {
+            for b in sys_errors {
+                let (x, y) = (Error::Sys(a), Error::Sys(b));
+                assert_eq!(x.cmp(&y) == Ordering::Equal, x == y, "{:?} {:?}", a, b);
+                let (x, y) = (eip712::Error::Sys(a), eip712::Error::Sys(b));
+                assert_eq!(x.cmp(&y) == Ordering::Equal, x == y, "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_duplicate_action_outside_group() {
+        // Only witness 1 belongs to current script group
+        MockTransaction {
+            input_count: 2,
+            group_inputs: vec![1],
+            witnesses: vec![action_witness(), action_witness()],
+            ..Default::default()
+        }
+        .install();
+        assert_eq!(
+            fetch_sighash_with_action().map(|_| ()),
+            Err(Error::DuplicateAction {
+                first: 0,
+                second: 1
+            })
+        );
+        assert!(fetch_sighash_with_action_from(Source::GroupInput).is_ok());
+
+        install_witnesses(vec![sighash_witness(), action_witness()]);
+        assert!(fetch_sighash_with_action().is_ok());
+    }
+
+    #[test]
+    fn test_assert_no_duplicate_actions() {
+        install_witnesses(vec![sighash_witness(), vec![]]);
+        assert_eq!(assert_no_duplicate_actions(Source::Input), Ok(()));
+
+        install_witnesses(vec![sighash_witness(), action_witness()]);
+        assert_eq!(assert_no_duplicate_actions(Source::Input), Ok(()));
+
+        install_witnesses(vec![action_witness(), sighash_witness(), action_witness()]);
+        assert_eq!(
+            assert_no_duplicate_actions(Source::Input),
+            Err(Error::DuplicateAction {
+                first: 0,
+                second: 2
+            })
+        );
+        // Witness 2 is not in the group
+        assert_eq!(assert_no_duplicate_actions(Source::GroupInput), Ok(()));
+    }
+
+    #[test]
+    fn test_calculate_inputs_len() {
+        for input_count in [1, 4, 5, MAX_INPUTS_LEN - 1, MAX_INPUTS_LEN] {
+            MockTransaction {
+                input_count,
+                ..Default::default()
+            }
+            .install();
+            assert_eq!(calculate_inputs_len(), Ok(input_count));
+        }
+        for input_count in [MAX_INPUTS_LEN + 1, usize::MAX] {
+            MockTransaction {
+                input_count,
+                ..Default::default()
+            }
+            .install();
+            assert_eq!(calculate_inputs_len(), Err(Error::InputCountOverflow));
+        }
+    }
+
+    #[test]
+    fn test_error_codes_unique() {
+        let sys_errors = [
+            SysError::IndexOutOfBound,
+            SysError::ItemMissing,
+            SysError::LengthNotEnough(0),
+            SysError::Encoding,
+            SysError::Unknown(0),
+        ];
+        let path = eip712::FieldPath::default();
+        let mut typed_message_errors = sys_errors.map(eip712::Error::Sys).to_vec();
+        typed_message_errors.extend([
+            eip712::Error::MoleculeEncoding,
+            eip712::Error::CellDataEof,
+            eip712::Error::InvalidSource(0),
+            eip712::Error::InvalidBool(path),
+            eip712::Error::InvalidNumber(path),
+            eip712::Error::InvalidFixedBytes(path),
+            eip712::Error::InvalidRefLength,
+            eip712::Error::OutputRefCell,
+            eip712::Error::ValueTooDeep,
+            eip712::Error::InvalidUtf8,
+            eip712::Error::InvalidRefFlag,
+            eip712::Error::NumberTooLarge,
+            eip712::Error::FieldCountMismatch(0),
+            eip712::Error::RefCellIndexOutOfBound,
+            eip712::Error::RefTransactionOutOfBound,
+            eip712::Error::UnexpectedValueType,
+        ]);
+        let mut errors = sys_errors.map(Error::Sys).to_vec();
+        errors.extend([
+            Error::DuplicateAction {
+                first: 0,
+                second: 1,
+            },
+            Error::MoleculeEncoding,
+            Error::NotTypedTransaction,
+            Error::NotSighashVariant,
+            Error::NonEmptyGroupWitness,
+            Error::NotOtxTransaction,
+            Error::InvalidOtxIndex,
+            Error::InvalidRecipientField,
+            Error::RecipientNotFound,
+            Error::ScanLimitExceeded,
+            Error::TypedMessage(eip712::Error::CellDataEof),
+            Error::MixedDomains,
+            Error::InvalidAddressField,
+            Error::MissingGroupWitness,
+            Error::InvalidSealLength,
+            Error::InputCountOverflow,
+            Error::SignatureVerification,
+            Error::ScriptArgsLength,
+            Error::UnexpectedTypedTransaction,
+            Error::UnknownWitnessVariant,
+            Error::EmptyAction,
+            Error::DuplicateOtxStart {
+                first: 0,
+                second: 1,
+            },
+        ]);
+
+        let codes = errors.iter().map(|e| e.code()).collect::<Vec<_>>();
+        let typed_message_codes = typed_message_errors
+            .iter()
+            .map(|e| e.code())
+            .collect::<Vec<_>>();
+        for codes in [&codes, &typed_message_codes] {
+            let mut sorted = codes.clone();
+            sorted.sort();
+            sorted.dedup();
+            assert_eq!(sorted.len(), codes.len());
+            assert!(!codes.contains(&0));
+        }
+        // Only syscall errors share codes between the two
+        for code in &typed_message_codes[sys_errors.len()..] {
+            assert!(!codes.contains(code), "{}", code);
+        }
+        for e in errors {
+            assert_eq!(i8::from(e), e.code());
+        }
+        for e in typed_message_errors {
+            assert_eq!(i8::from(e), e.code());
+        }
+    }
+
+    #[test]
+    fn test_extended_witness_tag() {
+        let witnesses = [
+            (ExtendedWitnessTag::SighashWithAction, action_witness()),
+            (ExtendedWitnessTag::Sighash, sighash_witness()),
+            (
+                ExtendedWitnessTag::Otx,
+                ExtendedWitness::new_builder()
+                    .set(Otx::default())
+                    .build()
+                    .as_slice()
+                    .to_vec(),
+            ),
+            (
+                ExtendedWitnessTag::OtxStart,
+                ExtendedWitness::new_builder()
+                    .set(OtxStart::default())
+                    .build()
+                    .as_slice()
+                    .to_vec(),
+            ),
+        ];
+        for (id, (tag, witness)) in (0xff000001..=0xff000004).zip(witnesses) {
+            assert_eq!(ExtendedWitnessTag::from_u32(id), Some(tag));
+            assert_eq!(tag.to_u32(), id);
+            assert_eq!(peek_extended_witness_tag(&witness), Some(tag));
+            let reader = parse_extended_witness(&witness).unwrap();
+            assert_eq!(ExtendedWitnessTag::from(&reader), tag);
+        }
+        assert_eq!(ExtendedWitnessTag::from_u32(0xff000000), None);
+        assert_eq!(ExtendedWitnessTag::from_u32(0xff000005), None);
+    }
+
+    #[cfg(feature = "profile")]
+    #[test]
+    fn test_sighash_all_hash_profiled() {
+        install_witnesses(vec![action_witness()]);
+        let (hash, count) = generate_sighash_all_hash_profiled().unwrap();
+        assert_eq!(Ok(hash), generate_sighash_all_hash());
+        // tx hash, the group witness, the probe beyond group witnesses, 3
+        // probes counting inputs, and the probe beyond extra witnesses
+        assert_eq!(count, 7);
+
+        // Each extra witness costs one more syscall
+        install_witnesses(vec![action_witness(), vec![1], vec![2]]);
+        let (_, more) = generate_sighash_all_hash_profiled().unwrap();
+        assert_eq!(more, count + 2);
+    }
+
+    #[test]
+    fn test_assert_action_recipient() {
+        let lock = |n: u8| Script::new_builder().args([n; 20][..].pack()).build();
+        let outputs = [lock(1), lock(2)]
+            .into_iter()
+            .map(|lock| CellOutput::new_builder().lock(lock).build())
+            .collect::<Vec<_>>();
+        let raw = RawTransaction::new_builder()
+            .outputs(CellOutputVec::new_builder().set(outputs).build())
+            .build();
+        MockTransaction {
+            transaction: Transaction::new_builder()
+                .raw(raw)
+                .build()
+                .as_slice()
+                .to_vec(),
+            ..Default::default()
+        }
+        .install();
+        let action = |recipient: &[u8]| {
+            let value = Value::new_builder()
+                .set(
+                    FixedBytes::new_builder()
+                        .set(recipient.iter().map(|b| Byte::new(*b)).collect())
+                        .build(),
+                )
+                .build();
+            let values = SerializedValues::new_builder()
+                .push(
+                    Bytes::new_builder()
+                        .set(value.as_slice().iter().map(|b| Byte::new(*b)).collect())
+                        .build(),
+                )
+                .build();
+            let eip712 = EIP712::new_builder()
+                .message(Struct::new_builder().values(values).build())
+                .build();
+            SighashWithAction::new_builder()
+                .message(TypedMessage::new_builder().set(eip712).build())
+                .build()
+        };
+
+        let present = action(lock(2).calc_script_hash().as_slice());
+        assert_eq!(assert_action_recipient(&present, 0), Ok(()));
+        assert_eq!(
+            assert_action_recipient(&present, 1),
+            Err(Error::InvalidRecipientField)
+        );
+        let absent = action(lock(3).calc_script_hash().as_slice());
+        assert_eq!(
+            assert_action_recipient(&absent, 0),
+            Err(Error::RecipientNotFound)
+        );
+        assert_eq!(
+            assert_action_recipient(&action(&[2; 20]), 0),
+            Err(Error::InvalidRecipientField)
+        );
+    }
+
+    #[test]
+    fn test_witness_contribution_hash() {
+        let install = |tx_hash: [u8; 32], witnesses: Vec<Vec<u8>>| {
+            MockTransaction {
+                tx_hash,
+                input_count: 1,
+                group_inputs: vec![0],
+                witnesses,
+                ..Default::default()
+            }
+            .install()
+        };
+        let witness = action_witness();
+        install([1; 32], vec![witness.clone(), vec![3, 4]]);
+        let contribution = witness_contribution_hash().unwrap();
+        assert_eq!(witness_contribution_hash(), Ok(contribution));
+
+        let mut hasher = new_blake2b();
+        hasher.update(&[SIGHASH_WITH_ACTION_TAG]);
+        hasher.update(SighashWithAction::default().message().as_slice());
+        hasher.update(&2u64.to_le_bytes());
+        hasher.update(&[3, 4]);
+        let mut expected = [0u8; 32];
+        hasher.finalize(&mut expected);
+        assert_eq!(contribution, expected);
+
+        // Independent of tx hash, unlike sighash-all message hash
+        let sighash = generate_sighash_all_hash().unwrap();
+        install([2; 32], vec![witness.clone(), vec![3, 4]]);
+        assert_eq!(witness_contribution_hash(), Ok(contribution));
+        assert_ne!(generate_sighash_all_hash(), Ok(sighash));
+
+        install([1; 32], vec![witness, vec![3, 5]]);
+        assert_ne!(witness_contribution_hash(), Ok(contribution));
+    }
+
+    #[test]
+    fn test_sighash_computation_trace() {
+        MockTransaction {
+            tx_hash: [1; 32],
+            input_count: 2,
+            group_inputs: vec![0],
+            witnesses: vec![action_witness(), vec![], vec![5], vec![6, 7]],
+            ..Default::default()
+        }
+        .install();
+        let trace = sighash_computation_trace().unwrap();
+        assert_eq!(
+            trace,
+            SighashTrace {
+                tx_hash: [1; 32],
+                action_tag: SIGHASH_WITH_ACTION_TAG,
+                message: Some(SighashWithAction::default().message().as_slice().to_vec()),
+                extra_witnesses: vec![vec![5], vec![6, 7]],
+            }
+        );
+        assert_eq!(Ok(trace.hash()), generate_sighash_all_hash());
+
+        install_witnesses(vec![sighash_witness()]);
+        let trace = sighash_computation_trace().unwrap();
+        assert_eq!(trace.action_tag, SIGHASH_TAG);
+        assert_eq!(trace.message, None);
+        assert_eq!(Ok(trace.hash()), generate_sighash_all_hash());
+    }
+
+    #[test]
+    fn test_assert_uniform_domain() {
+        let message = |domain: u8| {
+            let domain_separator = schemas::basic::Hash::new_builder()
+                .set(schemas::basic::Byte32::new_unchecked(
+                    vec![domain; 32].into(),
+                ))
+                .build();
+            let eip712 = EIP712::new_builder()
+                .domain_separator(domain_separator)
+                .build();
+            TypedMessage::new_builder().set(eip712).build()
+        };
+        let action = |domain: u8| {
+            let action = SighashWithAction::new_builder()
+                .message(message(domain))
+                .build();
+            ExtendedWitness::new_builder()
+                .set(action)
+                .build()
+                .as_slice()
+                .to_vec()
+        };
+        let otx = |domain: u8| {
+            let otx = Otx::new_builder().message(message(domain)).build();
+            ExtendedWitness::new_builder()
+                .set(otx)
+                .build()
+                .as_slice()
+                .to_vec()
+        };
+
+        install_witnesses(vec![action(1), vec![], otx(1), otx(1)]);
+        assert_eq!(assert_uniform_domain(), Ok([1; 32]));
+
+        install_witnesses(vec![action(1), otx(1), otx(2)]);
+        assert_eq!(assert_uniform_domain(), Err(Error::MixedDomains));
+
+        install_witnesses(vec![otx(2), sighash_witness(), action(1)]);
+        assert_eq!(assert_uniform_domain(), Err(Error::MixedDomains));
+
+        install_witnesses(vec![sighash_witness()]);
+        assert_eq!(assert_uniform_domain(), Err(Error::NotTypedTransaction));
+    }
+
+    #[test]
+    fn test_extra_witness_range() {
+        MockTransaction {
+            tx_hash: [1; 32],
+            input_count: 3,
+            group_inputs: vec![0],
+            witnesses: vec![sighash_witness(), vec![], vec![], vec![4], vec![5, 6]],
+            ..Default::default()
+        }
+        .install();
+        assert_eq!(extra_witness_range(), Ok(3..));
+
+        // Exactly the witnesses in the range are hashed
+        let mut hasher = new_blake2b();
+        hasher.update(&[1; 32]);
+        hasher.update(&[SIGHASH_TAG]);
+        for witness in [&[4][..], &[5, 6]] {
+            hasher.update(&(witness.len() as u64).to_le_bytes());
+            hasher.update(witness);
+        }
+        let mut expected = [0u8; 32];
+        hasher.finalize(&mut expected);
+        assert_eq!(generate_sighash_all_hash(), Ok(expected));
+    }
+
+    #[test]
+    fn test_missing_group_witness() {
+        // Group input exists, but has no witness
+        MockTransaction {
+            input_count: 2,
+            group_inputs: vec![1],
+            witnesses: vec![sighash_witness()],
+            ..Default::default()
+        }
+        .install();
+        assert_eq!(generate_sighash_all_hash(), Err(Error::MissingGroupWitness));
+
+        // Empty group
+        MockTransaction {
+            input_count: 1,
+            witnesses: vec![sighash_witness()],
+            ..Default::default()
+        }
+        .install();
+        assert_eq!(generate_sighash_all_hash(), Err(Error::MissingGroupWitness));
+    }
+
+    #[test]
+    fn test_ensure_trailing_group_witnesses_empty() {
+        let install = |witnesses: Vec<Vec<u8>>| {
+            MockTransaction {
+                input_count: 3,
+                group_inputs: vec![0, 1, 2],
+                witnesses,
+                ..Default::default()
+            }
+            .install()
+        };
+
+        install(vec![sighash_witness(), vec![], vec![]]);
+        assert_eq!(ensure_trailing_group_witnesses_empty(), Ok(()));
+        assert!(generate_sighash_all_hash().is_ok());
+
+        install(vec![sighash_witness(), vec![], vec![1]]);
+        assert_eq!(
+            ensure_trailing_group_witnesses_empty(),
+            Err(Error::NonEmptyGroupWitness)
+        );
+        assert_eq!(
+            generate_sighash_all_hash(),
+            Err(Error::NonEmptyGroupWitness)
+        );
+    }
+
+    #[test]
+    fn test_sighash_all_hash_variant() {
+        MockTransaction {
+            tx_hash: [1; 32],
+            input_count: 1,
+            group_inputs: vec![0],
+            witnesses: vec![action_witness(), vec![2, 3]],
+            ..Default::default()
+        }
+        .install();
+        let message = SighashWithAction::default().message();
+        let expected = |message_length: bool| {
+            let mut hasher = new_blake2b();
+            hasher.update(&[1; 32]);
+            hasher.update(&[SIGHASH_WITH_ACTION_TAG]);
+            if message_length {
+                hasher.update(&(message.as_slice().len() as u64).to_le_bytes());
+            }
+            hasher.update(message.as_slice());
+            hasher.update(&2u64.to_le_bytes());
+            hasher.update(&[2, 3]);
+            let mut output = [0u8; 32];
+            hasher.finalize(&mut output);
+            output
+        };
+
+        assert_eq!(HashVariant::default(), HashVariant::V1NoMessageLength);
+        assert_eq!(
+            generate_sighash_all_hash_variant(HashVariant::V1NoMessageLength),
+            Ok(expected(false))
+        );
+        assert_eq!(generate_sighash_all_hash(), Ok(expected(false)));
+        assert_eq!(
+            generate_sighash_all_hash_variant(HashVariant::V2MessageLength),
+            Ok(expected(true))
+        );
+
+        // Without a message, both variants agree
+        install_witnesses(vec![sighash_witness()]);
+        assert_eq!(
+            generate_sighash_all_hash_variant(HashVariant::V1NoMessageLength),
+            generate_sighash_all_hash_variant(HashVariant::V2MessageLength)
+        );
+    }
+
+    #[test]
+    fn test_sighash_all_hash_n() {
+        MockTransaction {
+            tx_hash: [1; 32],
+            input_count: 1,
+            group_inputs: vec![0],
+            witnesses: vec![sighash_witness(), vec![2]],
+            ..Default::default()
+        }
+        .install();
+
+        let mut hasher = Blake2bBuilder::new(64)
+            .personal(b"ckb-default-hash")
+            .build();
+        hasher.update(&[1; 32]);
+        hasher.update(&[SIGHASH_TAG]);
+        hasher.update(&1u64.to_le_bytes());
+        hasher.update(&[2]);
+        let mut expected = [0u8; 64];
+        hasher.finalize(&mut expected);
+
+        let hash = generate_sighash_all_hash_n::<64>().unwrap();
+        assert_eq!(hash, expected);
+        // Not a truncation of the 32-byte hash
+        let short = generate_sighash_all_hash_n::<32>().unwrap();
+        assert_eq!(Ok(short), generate_sighash_all_hash());
+        assert_ne!(&hash[..32], &short[..]);
+    }
+}
@@ -0,0 +1,369 @@
+//! Open transaction(otx) support. An aggregated CKB transaction might be
+//! assembled from multiple otxs, each of which is signed separately. The
+//! witness layout follows CKB cobuild: a single OtxStart witness marks where
+//! the otx region begins, each Otx witness immediately following OtxStart
+//! then describes one otx, in the same order as the cells they contribute.
+
+use crate::{
+    extended_witnesses, parse_extended_witness,
+    schemas::{
+        basic::{OtxReader, OtxStart, OtxStartReader, Uint32Reader},
+        top_level::{ExtendedWitnessUnion, ExtendedWitnessUnionReader},
+    },
+    syscalls::{load_cell, load_full_cell_data, load_full_transaction, load_input, load_witness},
+    Error,
+};
+use blake2b_ref::{Blake2b, Blake2bBuilder};
+use ckb_std::{ckb_constants::Source, error::SysError};
+use core::ops::Range;
+use molecule::prelude::{Entity, Reader};
+
+/// Cells covered by a single otx, each range holds indices in the full
+/// transaction.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct OtxRanges {
+    pub inputs: Range<usize>,
+    pub outputs: Range<usize>,
+    pub cell_deps: Range<usize>,
+    pub header_deps: Range<usize>,
+}
+
+impl OtxRanges {
+    /// Empty ranges positioned at the start of the otx region, the first
+    /// otx's ranges are built by calling `following` on this value.
+    pub fn starting_at(otx_start: &OtxStartReader) -> Self {
+        let input = read_u32(&otx_start.start_input_cell());
+        let output = read_u32(&otx_start.start_output_cell());
+        let cell_dep = read_u32(&otx_start.start_cell_deps());
+        let header_dep = read_u32(&otx_start.start_header_deps());
+        OtxRanges {
+            inputs: input..input,
+            outputs: output..output,
+            cell_deps: cell_dep..cell_dep,
+            header_deps: header_dep..header_dep,
+        }
+    }
+
+    /// Ranges of the otx right after current one. `InvalidOtxIndex` is
+    /// returned when any range of the otx would end beyond `usize::MAX`.
+    pub fn following(&self, otx: &OtxReader) -> Result<Self, Error> {
+        let next = |r: &Range<usize>, count: &Uint32Reader| {
+            let end = r.end.checked_add(read_u32(count));
+            end.map(|end| r.end..end).ok_or(Error::InvalidOtxIndex)
+        };
+        Ok(OtxRanges {
+            inputs: next(&self.inputs, &otx.input_cells())?,
+            outputs: next(&self.outputs, &otx.output_cells())?,
+            cell_deps: next(&self.cell_deps, &otx.cell_deps())?,
+            header_deps: next(&self.header_deps, &otx.header_deps())?,
+        })
+    }
+}
+
+fn read_u32(n: &Uint32Reader) -> usize {
+    let mut t = [0u8; 4];
+    t.copy_from_slice(n.raw_data());
+    u32::from_le_bytes(t) as usize
+}
+
+/// Locates the OtxStart witness among all witnesses of current transaction,
+/// returning `None` for transactions without an otx region. At most one
+/// OtxStart may appear, just like SighashWithAction, a second one yields
+/// `DuplicateOtxStart` with the witness indices of both.
+pub fn find_otx_start() -> Result<Option<OtxStart>, Error> {
+    Ok(find_unique_otx_start()?.map(|(_, s)| s))
+}
+
+fn find_unique_otx_start() -> Result<Option<(usize, OtxStart)>, Error> {
+    let mut found = None;
+    for item in extended_witnesses(Source::Input) {
+        if let (i, ExtendedWitnessUnion::OtxStart(s)) = item? {
+            if let Some((first, _)) = found {
+                return Err(Error::DuplicateOtxStart { first, second: i });
+            }
+            found = Some((i, s));
+        }
+    }
+    Ok(found)
+}
+
+fn locate_otx_start() -> Result<(usize, OtxStart), Error> {
+    find_unique_otx_start()?.ok_or(Error::NotOtxTransaction)
+}
+
+/// Generates signing message hash for the otx at `otx_index`, counting from
+/// the first Otx witness following OtxStart. Following CKB cobuild, the
+/// hasher is 32-byte blake2b personalized with `ckb-tcob-otxhash`, so an
+/// otx hash never collides with sighash-all message hash. Only the cells
+/// belonging to this particular otx are hashed, in the following order:
+///
+/// * The molecule serialized message
+/// * Input cell count(u32), then CellInput, CellOutput, data length(u32)
+///   and data of each input cell
+/// * Output cell count(u32), then CellOutput, data length(u32) and data of
+///   each output cell
+/// * Cell dep count(u32), then each CellDep
+/// * Header dep count(u32), then each header dep hash
+///
+/// All integers are in little endian, the counts are the Uint32 values kept
+/// in the Otx witness.
+pub fn generate_otx_hash(otx_index: usize) -> Result<[u8; 32], Error> {
+    let (start_index, otx_start) = locate_otx_start()?;
+    let mut ranges = OtxRanges::starting_at(&otx_start.as_reader());
+    let mut i = 0;
+    loop {
+        let witness = match load_witness(start_index + 1 + i, Source::Input) {
+            Ok(w) => w,
+            Err(SysError::IndexOutOfBound) => return Err(Error::InvalidOtxIndex),
+            Err(e) => return Err(e.into()),
+        };
+        let otx = match parse_extended_witness(&witness) {
+            Ok(ExtendedWitnessUnionReader::Otx(otx)) => otx,
+            _ => return Err(Error::InvalidOtxIndex),
+        };
+        ranges = ranges.following(&otx)?;
+        if i == otx_index {
+            return hash_otx(&otx, &ranges);
+        }
+        i += 1;
+    }
+}
+
+fn new_otx_blake2b() -> Blake2b {
+    Blake2bBuilder::new(32)
+        .personal(b"ckb-tcob-otxhash")
+        .build()
+}
+
+fn hash_otx(otx: &OtxReader, ranges: &OtxRanges) -> Result<[u8; 32], Error> {
+    let mut hasher = new_otx_blake2b();
+    hasher.update(otx.message().as_slice());
+
+    hasher.update(otx.input_cells().as_slice());
+    for i in ranges.inputs.clone() {
+        hasher.update(load_input(i, Source::Input)?.as_slice());
+        hasher.update(load_cell(i, Source::Input)?.as_slice());
+        let data = load_full_cell_data(i, Source::Input)?;
+        hasher.update(&(data.len() as u32).to_le_bytes());
+        hasher.update(&data);
+    }
+
+    hasher.update(otx.output_cells().as_slice());
+    for i in ranges.outputs.clone() {
+        hasher.update(load_cell(i, Source::Output)?.as_slice());
+        let data = load_full_cell_data(i, Source::Output)?;
+        hasher.update(&(data.len() as u32).to_le_bytes());
+        hasher.update(&data);
+    }
+
+    // There is no dedicated syscall for cell deps & header deps, they can
+    // only be read from the full transaction.
+    let tx = if !ranges.cell_deps.is_empty() || !ranges.header_deps.is_empty() {
+        Some(load_full_transaction()?.raw())
+    } else {
+        None
+    };
+    hasher.update(otx.cell_deps().as_slice());
+    if let Some(raw) = &tx {
+        for i in ranges.cell_deps.clone() {
+            let cell_dep = raw.cell_deps().get(i).ok_or(SysError::IndexOutOfBound)?;
+            hasher.update(cell_dep.as_slice());
+        }
+    }
+    hasher.update(otx.header_deps().as_slice());
+    if let Some(raw) = &tx {
+        for i in ranges.header_deps.clone() {
+            let header_dep = raw.header_deps().get(i).ok_or(SysError::IndexOutOfBound)?;
+            hasher.update(header_dep.as_slice());
+        }
+    }
+
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        schemas::{
+            basic::{Otx, Uint32},
+            top_level::ExtendedWitness,
+        },
+        MockTransaction,
+    };
+    use alloc::{format, string::String, vec, vec::Vec};
+    use ckb_std::ckb_types::{
+        packed::{CellInput, CellInputVec, CellOutput, RawTransaction, Transaction},
+        prelude::*,
+    };
+    use molecule::prelude::Builder;
+
+    fn hex(h: [u8; 32]) -> String {
+        h.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn uint32(n: u32) -> Uint32 {
+        Uint32::new_unchecked(n.to_le_bytes().to_vec().into())
+    }
+
+    fn otx(input_cells: u32) -> Otx {
+        Otx::new_builder().input_cells(uint32(input_cells)).build()
+    }
+
+    // Installs a transaction with `input_count` inputs, whose otx region
+    // starts at input `start_input` and holds a single otx
+    fn install(input_count: usize, start_input: u32, otx: &Otx) {
+        let otx_start = OtxStart::new_builder()
+            .start_input_cell(uint32(start_input))
+            .build();
+        let inputs = (0..input_count)
+            .map(|i| CellInput::new_builder().since((i as u64).pack()).build())
+            .collect::<Vec<_>>();
+        let raw = RawTransaction::new_builder()
+            .inputs(CellInputVec::new_builder().set(inputs).build())
+            .build();
+        MockTransaction {
+            input_count,
+            witnesses: vec![
+                ExtendedWitness::new_builder()
+                    .set(otx_start)
+                    .build()
+                    .as_slice()
+                    .to_vec(),
+                ExtendedWitness::new_builder()
+                    .set(otx.clone())
+                    .build()
+                    .as_slice()
+                    .to_vec(),
+            ],
+            input_cell_data: vec![vec![1], vec![2, 3]],
+            transaction: Transaction::new_builder()
+                .raw(raw)
+                .build()
+                .as_slice()
+                .to_vec(),
+            ..Default::default()
+        }
+        .install();
+    }
+
+    #[test]
+    fn test_empty_range() {
+        let otx = otx(0);
+        install(2, 2, &otx);
+
+        let mut hasher = new_otx_blake2b();
+        hasher.update(otx.message().as_slice());
+        for count in [otx.input_cells(), otx.output_cells()] {
+            hasher.update(count.as_slice());
+        }
+        hasher.update(otx.cell_deps().as_slice());
+        hasher.update(otx.header_deps().as_slice());
+        let mut expected = [0u8; 32];
+        hasher.finalize(&mut expected);
+
+        assert_eq!(generate_otx_hash(0), Ok(expected));
+    }
+
+    #[test]
+    fn test_range_at_end_of_inputs() {
+        let otx = otx(1);
+        install(2, 1, &otx);
+
+        let mut hasher = new_otx_blake2b();
+        hasher.update(otx.message().as_slice());
+        hasher.update(otx.input_cells().as_slice());
+        let input = CellInput::new_builder().since(1u64.pack()).build();
+        hasher.update(input.as_slice());
+        hasher.update(CellOutput::default().as_slice());
+        hasher.update(&2u32.to_le_bytes());
+        hasher.update(&[2, 3]);
+        hasher.update(otx.output_cells().as_slice());
+        hasher.update(otx.cell_deps().as_slice());
+        hasher.update(otx.header_deps().as_slice());
+        let mut expected = [0u8; 32];
+        hasher.finalize(&mut expected);
+
+        assert_eq!(generate_otx_hash(0), Ok(expected));
+        // Fixes the wire format, independently of the layout above
+        assert_eq!(
+            hex(expected),
+            "8ac50d429ef9958f0d2ff904f984f6e1ea4c9a35057541ce4c5168f563392875"
+        );
+    }
+
+    #[test]
+    fn test_range_beyond_inputs() {
+        install(2, 1, &otx(2));
+        assert_eq!(
+            generate_otx_hash(0),
+            Err(Error::Sys(SysError::IndexOutOfBound))
+        );
+    }
+
+    #[test]
+    fn test_range_overflow() {
+        let ranges = OtxRanges {
+            inputs: usize::MAX..usize::MAX,
+            outputs: 0..0,
+            cell_deps: 0..0,
+            header_deps: 0..0,
+        };
+        assert_eq!(ranges.following(&otx(0).as_reader()), Ok(ranges.clone()));
+        assert_eq!(
+            ranges.following(&otx(1).as_reader()),
+            Err(Error::InvalidOtxIndex)
+        );
+    }
+
+    #[test]
+    fn test_missing_otx() {
+        install(2, 0, &otx(0));
+        assert_eq!(generate_otx_hash(1), Err(Error::InvalidOtxIndex));
+    }
+
+    #[test]
+    fn test_find_otx_start() {
+        let otx_start = || {
+            ExtendedWitness::new_builder()
+                .set(OtxStart::default())
+                .build()
+                .as_slice()
+                .to_vec()
+        };
+        let install_witnesses = |witnesses| {
+            MockTransaction {
+                input_count: 1,
+                witnesses,
+                ..Default::default()
+            }
+            .install()
+        };
+        let found = || find_otx_start().map(|s| s.map(|s| s.as_slice().to_vec()));
+
+        install_witnesses(vec![vec![], vec![1]]);
+        assert_eq!(found(), Ok(None));
+        assert_eq!(generate_otx_hash(0), Err(Error::NotOtxTransaction));
+
+        install_witnesses(vec![vec![], otx_start()]);
+        assert_eq!(found(), Ok(Some(OtxStart::default().as_slice().to_vec())));
+
+        install_witnesses(vec![otx_start(), vec![], otx_start()]);
+        assert_eq!(
+            found(),
+            Err(Error::DuplicateOtxStart {
+                first: 0,
+                second: 2
+            })
+        );
+        assert_eq!(
+            generate_otx_hash(0),
+            Err(Error::DuplicateOtxStart {
+                first: 0,
+                second: 2
+            })
+        );
+    }
+}
@@ -2857,6 +2857,7 @@ impl ::core::fmt::Display for RefCell {
         write!(f, "{}: {}", "source", self.source())?;
         write!(f, ", {}: {}", "index", self.index())?;
         write!(f, ", {}: {}", "offset", self.offset())?;
+        write!(f, ", {}: {}", "keccak", self.keccak())?;
         write!(f, " }}")
     }
 }
@@ -2867,10 +2868,10 @@ impl ::core::default::Default for RefCell {
     }
 }
 impl RefCell {
-    const DEFAULT_VALUE: [u8; 16] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
-    pub const TOTAL_SIZE: usize = 16;
-    pub const FIELD_SIZES: [usize; 3] = [8, 4, 4];
-    pub const FIELD_COUNT: usize = 3;
+    const DEFAULT_VALUE: [u8; 17] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    pub const TOTAL_SIZE: usize = 17;
+    pub const FIELD_SIZES: [usize; 4] = [8, 4, 4, 1];
+    pub const FIELD_COUNT: usize = 4;
     pub fn source(&self) -> Uint64 {
         Uint64::new_unchecked(self.0.slice(0..8))
     }
@@ -2880,6 +2881,9 @@ impl RefCell {
     pub fn offset(&self) -> Uint32 {
         Uint32::new_unchecked(self.0.slice(12..16))
     }
+    pub fn keccak(&self) -> Bool {
+        Bool::new_unchecked(self.0.slice(16..17))
+    }
     pub fn as_reader<'r>(&'r self) -> RefCellReader<'r> {
         RefCellReader::new_unchecked(self.as_slice())
     }
@@ -2910,6 +2914,7 @@ impl molecule::prelude::Entity for RefCell {
             .source(self.source())
             .index(self.index())
             .offset(self.offset())
+            .keccak(self.keccak())
     }
 }
 #[derive(Clone, Copy)]
@@ -2934,13 +2939,14 @@ impl<'r> ::core::fmt::Display for RefCellReader<'r> {
         write!(f, "{}: {}", "source", self.source())?;
         write!(f, ", {}: {}", "index", self.index())?;
         write!(f, ", {}: {}", "offset", self.offset())?;
+        write!(f, ", {}: {}", "keccak", self.keccak())?;
         write!(f, " }}")
     }
 }
 impl<'r> RefCellReader<'r> {
-    pub const TOTAL_SIZE: usize = 16;
-    pub const FIELD_SIZES: [usize; 3] = [8, 4, 4];
-    pub const FIELD_COUNT: usize = 3;
+    pub const TOTAL_SIZE: usize = 17;
+    pub const FIELD_SIZES: [usize; 4] = [8, 4, 4, 1];
+    pub const FIELD_COUNT: usize = 4;
     pub fn source(&self) -> Uint64Reader<'r> {
         Uint64Reader::new_unchecked(&self.as_slice()[0..8])
     }
@@ -2950,6 +2956,9 @@ impl<'r> RefCellReader<'r> {
     pub fn offset(&self) -> Uint32Reader<'r> {
         Uint32Reader::new_unchecked(&self.as_slice()[12..16])
     }
+    pub fn keccak(&self) -> BoolReader<'r> {
+        BoolReader::new_unchecked(&self.as_slice()[16..17])
+    }
 }
 impl<'r> molecule::prelude::Reader<'r> for RefCellReader<'r> {
     type Entity = RefCell;
@@ -2977,11 +2986,12 @@ pub struct RefCellBuilder {
     pub(crate) source: Uint64,
     pub(crate) index: Uint32,
     pub(crate) offset: Uint32,
+    pub(crate) keccak: Bool,
 }
 impl RefCellBuilder {
-    pub const TOTAL_SIZE: usize = 16;
-    pub const FIELD_SIZES: [usize; 3] = [8, 4, 4];
-    pub const FIELD_COUNT: usize = 3;
+    pub const TOTAL_SIZE: usize = 17;
+    pub const FIELD_SIZES: [usize; 4] = [8, 4, 4, 1];
+    pub const FIELD_COUNT: usize = 4;
     pub fn source(mut self, v: Uint64) -> Self {
         self.source = v;
         self
@@ -2994,6 +3004,10 @@ impl RefCellBuilder {
         self.offset = v;
         self
     }
+    pub fn keccak(mut self, v: Bool) -> Self {
+        self.keccak = v;
+        self
+    }
 }
 impl molecule::prelude::Builder for RefCellBuilder {
     type Entity = RefCell;
@@ -3005,6 +3019,7 @@ impl molecule::prelude::Builder for RefCellBuilder {
         writer.write_all(self.source.as_slice())?;
         writer.write_all(self.index.as_slice())?;
         writer.write_all(self.offset.as_slice())?;
+        writer.write_all(self.keccak.as_slice())?;
         Ok(())
     }
     fn build(&self) -> Self::Entity {
@@ -3034,6 +3049,8 @@ impl ::core::fmt::Display for RefTransaction {
     fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
         write!(f, "{} {{ ", Self::NAME)?;
         write!(f, "{}: {}", "offset", self.offset())?;
+        write!(f, ", {}: {}", "length", self.length())?;
+        write!(f, ", {}: {}", "keccak", self.keccak())?;
         write!(f, " }}")
     }
 }
@@ -3044,13 +3061,19 @@ impl ::core::default::Default for RefTransaction {
     }
 }
 impl RefTransaction {
-    const DEFAULT_VALUE: [u8; 4] = [0, 0, 0, 0];
-    pub const TOTAL_SIZE: usize = 4;
-    pub const FIELD_SIZES: [usize; 1] = [4];
-    pub const FIELD_COUNT: usize = 1;
+    const DEFAULT_VALUE: [u8; 9] = [0, 0, 0, 0, 0, 0, 0, 0, 0];
+    pub const TOTAL_SIZE: usize = 9;
+    pub const FIELD_SIZES: [usize; 3] = [4, 4, 1];
+    pub const FIELD_COUNT: usize = 3;
     pub fn offset(&self) -> Uint32 {
         Uint32::new_unchecked(self.0.slice(0..4))
     }
+    pub fn length(&self) -> Uint32 {
+        Uint32::new_unchecked(self.0.slice(4..8))
+    }
+    pub fn keccak(&self) -> Bool {
+        Bool::new_unchecked(self.0.slice(8..9))
+    }
     pub fn as_reader<'r>(&'r self) -> RefTransactionReader<'r> {
         RefTransactionReader::new_unchecked(self.as_slice())
     }
@@ -3077,7 +3100,10 @@ impl molecule::prelude::Entity for RefTransaction {
         ::core::default::Default::default()
     }
     fn as_builder(self) -> Self::Builder {
-        Self::new_builder().offset(self.offset())
+        Self::new_builder()
+            .offset(self.offset())
+            .length(self.length())
+            .keccak(self.keccak())
     }
 }
 #[derive(Clone, Copy)]
@@ -3100,16 +3126,24 @@ impl<'r> ::core::fmt::Display for RefTransactionReader<'r> {
     fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
         write!(f, "{} {{ ", Self::NAME)?;
         write!(f, "{}: {}", "offset", self.offset())?;
+        write!(f, ", {}: {}", "length", self.length())?;
+        write!(f, ", {}: {}", "keccak", self.keccak())?;
         write!(f, " }}")
     }
 }
 impl<'r> RefTransactionReader<'r> {
-    pub const TOTAL_SIZE: usize = 4;
-    pub const FIELD_SIZES: [usize; 1] = [4];
-    pub const FIELD_COUNT: usize = 1;
+    pub const TOTAL_SIZE: usize = 9;
+    pub const FIELD_SIZES: [usize; 3] = [4, 4, 1];
+    pub const FIELD_COUNT: usize = 3;
     pub fn offset(&self) -> Uint32Reader<'r> {
         Uint32Reader::new_unchecked(&self.as_slice()[0..4])
     }
+    pub fn length(&self) -> Uint32Reader<'r> {
+        Uint32Reader::new_unchecked(&self.as_slice()[4..8])
+    }
+    pub fn keccak(&self) -> BoolReader<'r> {
+        BoolReader::new_unchecked(&self.as_slice()[8..9])
+    }
 }
 impl<'r> molecule::prelude::Reader<'r> for RefTransactionReader<'r> {
     type Entity = RefTransaction;
@@ -3135,15 +3169,25 @@ impl<'r> molecule::prelude::Reader<'r> for RefTransactionReader<'r> {
 #[derive(Debug, Default)]
 pub struct RefTransactionBuilder {
     pub(crate) offset: Uint32,
+    pub(crate) length: Uint32,
+    pub(crate) keccak: Bool,
 }
 impl RefTransactionBuilder {
-    pub const TOTAL_SIZE: usize = 4;
-    pub const FIELD_SIZES: [usize; 1] = [4];
-    pub const FIELD_COUNT: usize = 1;
+    pub const TOTAL_SIZE: usize = 9;
+    pub const FIELD_SIZES: [usize; 3] = [4, 4, 1];
+    pub const FIELD_COUNT: usize = 3;
     pub fn offset(mut self, v: Uint32) -> Self {
         self.offset = v;
         self
     }
+    pub fn length(mut self, v: Uint32) -> Self {
+        self.length = v;
+        self
+    }
+    pub fn keccak(mut self, v: Bool) -> Self {
+        self.keccak = v;
+        self
+    }
 }
 impl molecule::prelude::Builder for RefTransactionBuilder {
     type Entity = RefTransaction;
@@ -3153,6 +3197,8 @@ impl molecule::prelude::Builder for RefTransactionBuilder {
     }
     fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
         writer.write_all(self.offset.as_slice())?;
+        writer.write_all(self.length.as_slice())?;
+        writer.write_all(self.keccak.as_slice())?;
         Ok(())
     }
     fn build(&self) -> Self::Entity {
@@ -3163,6 +3209,183 @@ impl molecule::prelude::Builder for RefTransactionBuilder {
     }
 }
 #[derive(Clone)]
+pub struct RefScript(molecule::bytes::Bytes);
+impl ::core::fmt::LowerHex for RefScript {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl ::core::fmt::Debug for RefScript {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl ::core::fmt::Display for RefScript {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} {{ ", Self::NAME)?;
+        let extra_count = self.count_extra_fields();
+        if extra_count != 0 {
+            write!(f, ".. ({} fields)", extra_count)?;
+        }
+        write!(f, " }}")
+    }
+}
+impl ::core::default::Default for RefScript {
+    fn default() -> Self {
+        let v = molecule::bytes::Bytes::from_static(&Self::DEFAULT_VALUE);
+        RefScript::new_unchecked(v)
+    }
+}
+impl RefScript {
+    const DEFAULT_VALUE: [u8; 4] = [4, 0, 0, 0];
+    pub const FIELD_COUNT: usize = 0;
+    pub fn total_size(&self) -> usize {
+        molecule::unpack_number(self.as_slice()) as usize
+    }
+    pub fn field_count(&self) -> usize {
+        if self.total_size() == molecule::NUMBER_SIZE {
+            0
+        } else {
+            (molecule::unpack_number(&self.as_slice()[molecule::NUMBER_SIZE..]) as usize / 4) - 1
+        }
+    }
+    pub fn count_extra_fields(&self) -> usize {
+        self.field_count() - Self::FIELD_COUNT
+    }
+    pub fn has_extra_fields(&self) -> bool {
+        Self::FIELD_COUNT != self.field_count()
+    }
+    pub fn as_reader<'r>(&'r self) -> RefScriptReader<'r> {
+        RefScriptReader::new_unchecked(self.as_slice())
+    }
+}
+impl molecule::prelude::Entity for RefScript {
+    type Builder = RefScriptBuilder;
+    const NAME: &'static str = "RefScript";
+    fn new_unchecked(data: molecule::bytes::Bytes) -> Self {
+        RefScript(data)
+    }
+    fn as_bytes(&self) -> molecule::bytes::Bytes {
+        self.0.clone()
+    }
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+    fn from_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        RefScriptReader::from_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn from_compatible_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        RefScriptReader::from_compatible_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn new_builder() -> Self::Builder {
+        ::core::default::Default::default()
+    }
+    fn as_builder(self) -> Self::Builder {
+        Self::new_builder()
+    }
+}
+#[derive(Clone, Copy)]
+pub struct RefScriptReader<'r>(&'r [u8]);
+impl<'r> ::core::fmt::LowerHex for RefScriptReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl<'r> ::core::fmt::Debug for RefScriptReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl<'r> ::core::fmt::Display for RefScriptReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} {{ ", Self::NAME)?;
+        let extra_count = self.count_extra_fields();
+        if extra_count != 0 {
+            write!(f, ".. ({} fields)", extra_count)?;
+        }
+        write!(f, " }}")
+    }
+}
+impl<'r> RefScriptReader<'r> {
+    pub const FIELD_COUNT: usize = 0;
+    pub fn total_size(&self) -> usize {
+        molecule::unpack_number(self.as_slice()) as usize
+    }
+    pub fn field_count(&self) -> usize {
+        if self.total_size() == molecule::NUMBER_SIZE {
+            0
+        } else {
+            (molecule::unpack_number(&self.as_slice()[molecule::NUMBER_SIZE..]) as usize / 4) - 1
+        }
+    }
+    pub fn count_extra_fields(&self) -> usize {
+        self.field_count() - Self::FIELD_COUNT
+    }
+    pub fn has_extra_fields(&self) -> bool {
+        Self::FIELD_COUNT != self.field_count()
+    }
+}
+impl<'r> molecule::prelude::Reader<'r> for RefScriptReader<'r> {
+    type Entity = RefScript;
+    const NAME: &'static str = "RefScriptReader";
+    fn to_entity(&self) -> Self::Entity {
+        Self::Entity::new_unchecked(self.as_slice().to_owned().into())
+    }
+    fn new_unchecked(slice: &'r [u8]) -> Self {
+        RefScriptReader(slice)
+    }
+    fn as_slice(&self) -> &'r [u8] {
+        self.0
+    }
+    fn verify(slice: &[u8], compatible: bool) -> molecule::error::VerificationResult<()> {
+        use molecule::verification_error as ve;
+        let slice_len = slice.len();
+        if slice_len < molecule::NUMBER_SIZE {
+            return ve!(Self, HeaderIsBroken, molecule::NUMBER_SIZE, slice_len);
+        }
+        let total_size = molecule::unpack_number(slice) as usize;
+        if slice_len != total_size {
+            return ve!(Self, TotalSizeNotMatch, total_size, slice_len);
+        }
+        if slice_len > molecule::NUMBER_SIZE && !compatible {
+            return ve!(Self, FieldCountNotMatch, Self::FIELD_COUNT, !0);
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Default)]
+pub struct RefScriptBuilder {}
+impl RefScriptBuilder {
+    pub const FIELD_COUNT: usize = 0;
+}
+impl molecule::prelude::Builder for RefScriptBuilder {
+    type Entity = RefScript;
+    const NAME: &'static str = "RefScriptBuilder";
+    fn expected_length(&self) -> usize {
+        molecule::NUMBER_SIZE
+    }
+    fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
+        writer.write_all(&molecule::pack_number(
+            molecule::NUMBER_SIZE as molecule::Number,
+        ))?;
+        Ok(())
+    }
+    fn build(&self) -> Self::Entity {
+        let mut inner = Vec::with_capacity(self.expected_length());
+        self.write(&mut inner)
+            .unwrap_or_else(|_| panic!("{} build should be ok", Self::NAME));
+        RefScript::new_unchecked(inner.into())
+    }
+}
+#[derive(Clone)]
 pub struct Hash(molecule::bytes::Bytes);
 impl ::core::fmt::LowerHex for Hash {
     fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
@@ -3196,7 +3419,7 @@ impl Hash {
         0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         0, 0, 0, 0, 0, 0,
     ];
-    pub const ITEMS_COUNT: usize = 3;
+    pub const ITEMS_COUNT: usize = 4;
     pub fn item_id(&self) -> molecule::Number {
         molecule::unpack_number(self.as_slice())
     }
@@ -3206,6 +3429,7 @@ impl Hash {
             0 => Byte32::new_unchecked(inner).into(),
             1 => RefCell::new_unchecked(inner).into(),
             2 => RefTransaction::new_unchecked(inner).into(),
+            3 => RefScript::new_unchecked(inner).into(),
             _ => panic!("{}: invalid data", Self::NAME),
         }
     }
@@ -3262,7 +3486,7 @@ impl<'r> ::core::fmt::Display for HashReader<'r> {
     }
 }
 impl<'r> HashReader<'r> {
-    pub const ITEMS_COUNT: usize = 3;
+    pub const ITEMS_COUNT: usize = 4;
     pub fn item_id(&self) -> molecule::Number {
         molecule::unpack_number(self.as_slice())
     }
@@ -3272,6 +3496,7 @@ impl<'r> HashReader<'r> {
             0 => Byte32Reader::new_unchecked(inner).into(),
             1 => RefCellReader::new_unchecked(inner).into(),
             2 => RefTransactionReader::new_unchecked(inner).into(),
+            3 => RefScriptReader::new_unchecked(inner).into(),
             _ => panic!("{}: invalid data", Self::NAME),
         }
     }
@@ -3300,6 +3525,7 @@ impl<'r> molecule::prelude::Reader<'r> for HashReader<'r> {
             0 => Byte32Reader::verify(inner_slice, compatible),
             1 => RefCellReader::verify(inner_slice, compatible),
             2 => RefTransactionReader::verify(inner_slice, compatible),
+            3 => RefScriptReader::verify(inner_slice, compatible),
             _ => ve!(Self, UnknownItem, Self::ITEMS_COUNT, item_id),
         }?;
         Ok(())
@@ -3308,7 +3534,7 @@ impl<'r> molecule::prelude::Reader<'r> for HashReader<'r> {
 #[derive(Debug, Default)]
 pub struct HashBuilder(pub(crate) HashUnion);
 impl HashBuilder {
-    pub const ITEMS_COUNT: usize = 3;
+    pub const ITEMS_COUNT: usize = 4;
     pub fn set<I>(mut self, v: I) -> Self
     where
         I: ::core::convert::Into<HashUnion>,
@@ -3339,12 +3565,14 @@ pub enum HashUnion {
     Byte32(Byte32),
     RefCell(RefCell),
     RefTransaction(RefTransaction),
+    RefScript(RefScript),
 }
 #[derive(Debug, Clone, Copy)]
 pub enum HashUnionReader<'r> {
     Byte32(Byte32Reader<'r>),
     RefCell(RefCellReader<'r>),
     RefTransaction(RefTransactionReader<'r>),
+    RefScript(RefScriptReader<'r>),
 }
 impl ::core::default::Default for HashUnion {
     fn default() -> Self {
@@ -3363,6 +3591,9 @@ impl ::core::fmt::Display for HashUnion {
             HashUnion::RefTransaction(ref item) => {
                 write!(f, "{}::{}({})", Self::NAME, RefTransaction::NAME, item)
             }
+            HashUnion::RefScript(ref item) => {
+                write!(f, "{}::{}({})", Self::NAME, RefScript::NAME, item)
+            }
         }
     }
 }
@@ -3378,6 +3609,9 @@ impl<'r> ::core::fmt::Display for HashUnionReader<'r> {
             HashUnionReader::RefTransaction(ref item) => {
                 write!(f, "{}::{}({})", Self::NAME, RefTransaction::NAME, item)
             }
+            HashUnionReader::RefScript(ref item) => {
+                write!(f, "{}::{}({})", Self::NAME, RefScript::NAME, item)
+            }
         }
     }
 }
@@ -3387,6 +3621,7 @@ impl HashUnion {
             HashUnion::Byte32(ref item) => write!(f, "{}", item),
             HashUnion::RefCell(ref item) => write!(f, "{}", item),
             HashUnion::RefTransaction(ref item) => write!(f, "{}", item),
+            HashUnion::RefScript(ref item) => write!(f, "{}", item),
         }
     }
 }
@@ -3396,6 +3631,7 @@ impl<'r> HashUnionReader<'r> {
             HashUnionReader::Byte32(ref item) => write!(f, "{}", item),
             HashUnionReader::RefCell(ref item) => write!(f, "{}", item),
             HashUnionReader::RefTransaction(ref item) => write!(f, "{}", item),
+            HashUnionReader::RefScript(ref item) => write!(f, "{}", item),
         }
     }
 }
@@ -3414,6 +3650,11 @@ impl ::core::convert::From<RefTransaction> for HashUnion {
         HashUnion::RefTransaction(item)
     }
 }
+impl ::core::convert::From<RefScript> for HashUnion {
+    fn from(item: RefScript) -> Self {
+        HashUnion::RefScript(item)
+    }
+}
 impl<'r> ::core::convert::From<Byte32Reader<'r>> for HashUnionReader<'r> {
     fn from(item: Byte32Reader<'r>) -> Self {
         HashUnionReader::Byte32(item)
@@ -3429,6 +3670,11 @@ impl<'r> ::core::convert::From<RefTransactionReader<'r>> for HashUnionReader<'r>
         HashUnionReader::RefTransaction(item)
     }
 }
+impl<'r> ::core::convert::From<RefScriptReader<'r>> for HashUnionReader<'r> {
+    fn from(item: RefScriptReader<'r>) -> Self {
+        HashUnionReader::RefScript(item)
+    }
+}
 impl HashUnion {
     pub const NAME: &'static str = "HashUnion";
     pub fn as_bytes(&self) -> molecule::bytes::Bytes {
@@ -3436,6 +3682,7 @@ impl HashUnion {
             HashUnion::Byte32(item) => item.as_bytes(),
             HashUnion::RefCell(item) => item.as_bytes(),
             HashUnion::RefTransaction(item) => item.as_bytes(),
+            HashUnion::RefScript(item) => item.as_bytes(),
         }
     }
     pub fn as_slice(&self) -> &[u8] {
@@ -3443,6 +3690,7 @@ impl HashUnion {
             HashUnion::Byte32(item) => item.as_slice(),
             HashUnion::RefCell(item) => item.as_slice(),
             HashUnion::RefTransaction(item) => item.as_slice(),
+            HashUnion::RefScript(item) => item.as_slice(),
         }
     }
     pub fn item_id(&self) -> molecule::Number {
@@ -3450,6 +3698,7 @@ impl HashUnion {
             HashUnion::Byte32(_) => 0,
             HashUnion::RefCell(_) => 1,
             HashUnion::RefTransaction(_) => 2,
+            HashUnion::RefScript(_) => 3,
         }
     }
     pub fn item_name(&self) -> &str {
@@ -3457,6 +3706,7 @@ impl HashUnion {
             HashUnion::Byte32(_) => "Byte32",
             HashUnion::RefCell(_) => "RefCell",
             HashUnion::RefTransaction(_) => "RefTransaction",
+            HashUnion::RefScript(_) => "RefScript",
         }
     }
     pub fn as_reader<'r>(&'r self) -> HashUnionReader<'r> {
@@ -3464,6 +3714,7 @@ impl HashUnion {
             HashUnion::Byte32(item) => item.as_reader().into(),
             HashUnion::RefCell(item) => item.as_reader().into(),
             HashUnion::RefTransaction(item) => item.as_reader().into(),
+            HashUnion::RefScript(item) => item.as_reader().into(),
         }
     }
 }
@@ -3474,6 +3725,7 @@ impl<'r> HashUnionReader<'r> {
             HashUnionReader::Byte32(item) => item.as_slice(),
             HashUnionReader::RefCell(item) => item.as_slice(),
             HashUnionReader::RefTransaction(item) => item.as_slice(),
+            HashUnionReader::RefScript(item) => item.as_slice(),
         }
     }
     pub fn item_id(&self) -> molecule::Number {
@@ -3481,6 +3733,7 @@ impl<'r> HashUnionReader<'r> {
             HashUnionReader::Byte32(_) => 0,
             HashUnionReader::RefCell(_) => 1,
             HashUnionReader::RefTransaction(_) => 2,
+            HashUnionReader::RefScript(_) => 3,
         }
     }
     pub fn item_name(&self) -> &str {
@@ -3488,6 +3741,7 @@ impl<'r> HashUnionReader<'r> {
             HashUnionReader::Byte32(_) => "Byte32",
             HashUnionReader::RefCell(_) => "RefCell",
             HashUnionReader::RefTransaction(_) => "RefTransaction",
+            HashUnionReader::RefScript(_) => "RefScript",
         }
     }
 }
@@ -4328,8 +4582,8 @@ impl molecule::prelude::Builder for ArrayBuilder {
     }
 }
 #[derive(Clone)]
-pub struct Value(molecule::bytes::Bytes);
-impl ::core::fmt::LowerHex for Value {
+pub struct RefBytes(molecule::bytes::Bytes);
+impl ::core::fmt::LowerHex for RefBytes {
     fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
         use molecule::hex_string;
         if f.alternate() {
@@ -4338,57 +4592,53 @@ impl ::core::fmt::LowerHex for Value {
         write!(f, "{}", hex_string(self.as_slice()))
     }
 }
-impl ::core::fmt::Debug for Value {
+impl ::core::fmt::Debug for RefBytes {
     fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
         write!(f, "{}({:#x})", Self::NAME, self)
     }
 }
-impl ::core::fmt::Display for Value {
+impl ::core::fmt::Display for RefBytes {
     fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
-        write!(f, "{}(", Self::NAME)?;
-        self.to_enum().display_inner(f)?;
-        write!(f, ")")
+        write!(f, "{} {{ ", Self::NAME)?;
+        write!(f, "{}: {}", "source", self.source())?;
+        write!(f, ", {}: {}", "index", self.index())?;
+        write!(f, ", {}: {}", "offset", self.offset())?;
+        write!(f, ", {}: {}", "length", self.length())?;
+        write!(f, " }}")
     }
 }
-impl ::core::default::Default for Value {
+impl ::core::default::Default for RefBytes {
     fn default() -> Self {
         let v = molecule::bytes::Bytes::from_static(&Self::DEFAULT_VALUE);
-        Value::new_unchecked(v)
+        RefBytes::new_unchecked(v)
     }
 }
-impl Value {
-    const DEFAULT_VALUE: [u8; 56] = [
-        0, 0, 0, 0, 52, 0, 0, 0, 12, 0, 0, 0, 48, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0,
-    ];
-    pub const ITEMS_COUNT: usize = 9;
-    pub fn item_id(&self) -> molecule::Number {
-        molecule::unpack_number(self.as_slice())
+impl RefBytes {
+    const DEFAULT_VALUE: [u8; 20] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    pub const TOTAL_SIZE: usize = 20;
+    pub const FIELD_SIZES: [usize; 4] = [8, 4, 4, 4];
+    pub const FIELD_COUNT: usize = 4;
+    pub fn source(&self) -> Uint64 {
+        Uint64::new_unchecked(self.0.slice(0..8))
     }
-    pub fn to_enum(&self) -> ValueUnion {
-        let inner = self.0.slice(molecule::NUMBER_SIZE..);
-        match self.item_id() {
-            0 => Struct::new_unchecked(inner).into(),
-            1 => Array::new_unchecked(inner).into(),
-            2 => Bool::new_unchecked(inner).into(),
-            3 => Bytes::new_unchecked(inner).into(),
-            4 => String::new_unchecked(inner).into(),
-            5 => Address::new_unchecked(inner).into(),
-            6 => FixedBytes::new_unchecked(inner).into(),
-            7 => Int::new_unchecked(inner).into(),
-            8 => Uint::new_unchecked(inner).into(),
-            _ => panic!("{}: invalid data", Self::NAME),
-        }
+    pub fn index(&self) -> Uint32 {
+        Uint32::new_unchecked(self.0.slice(8..12))
     }
-    pub fn as_reader<'r>(&'r self) -> ValueReader<'r> {
-        ValueReader::new_unchecked(self.as_slice())
+    pub fn offset(&self) -> Uint32 {
+        Uint32::new_unchecked(self.0.slice(12..16))
+    }
+    pub fn length(&self) -> Uint32 {
+        Uint32::new_unchecked(self.0.slice(16..20))
+    }
+    pub fn as_reader<'r>(&'r self) -> RefBytesReader<'r> {
+        RefBytesReader::new_unchecked(self.as_slice())
     }
 }
-impl molecule::prelude::Entity for Value {
-    type Builder = ValueBuilder;
-    const NAME: &'static str = "Value";
+impl molecule::prelude::Entity for RefBytes {
+    type Builder = RefBytesBuilder;
+    const NAME: &'static str = "RefBytes";
     fn new_unchecked(data: molecule::bytes::Bytes) -> Self {
-        Value(data)
+        RefBytes(data)
     }
     fn as_bytes(&self) -> molecule::bytes::Bytes {
         self.0.clone()
@@ -4397,21 +4647,25 @@ impl molecule::prelude::Entity for Value {
         &self.0[..]
     }
     fn from_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
-        ValueReader::from_slice(slice).map(|reader| reader.to_entity())
+        RefBytesReader::from_slice(slice).map(|reader| reader.to_entity())
     }
     fn from_compatible_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
-        ValueReader::from_compatible_slice(slice).map(|reader| reader.to_entity())
+        RefBytesReader::from_compatible_slice(slice).map(|reader| reader.to_entity())
     }
     fn new_builder() -> Self::Builder {
         ::core::default::Default::default()
     }
     fn as_builder(self) -> Self::Builder {
-        Self::new_builder().set(self.to_enum())
+        Self::new_builder()
+            .source(self.source())
+            .index(self.index())
+            .offset(self.offset())
+            .length(self.length())
     }
 }
 #[derive(Clone, Copy)]
-pub struct ValueReader<'r>(&'r [u8]);
-impl<'r> ::core::fmt::LowerHex for ValueReader<'r> {
+pub struct RefBytesReader<'r>(&'r [u8]);
+impl<'r> ::core::fmt::LowerHex for RefBytesReader<'r> {
     fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
         use molecule::hex_string;
         if f.alternate() {
@@ -4420,35 +4674,231 @@ impl<'r> ::core::fmt::LowerHex for ValueReader<'r> {
         write!(f, "{}", hex_string(self.as_slice()))
     }
 }
-impl<'r> ::core::fmt::Debug for ValueReader<'r> {
+impl<'r> ::core::fmt::Debug for RefBytesReader<'r> {
     fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
         write!(f, "{}({:#x})", Self::NAME, self)
     }
 }
-impl<'r> ::core::fmt::Display for ValueReader<'r> {
+impl<'r> ::core::fmt::Display for RefBytesReader<'r> {
     fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
-        write!(f, "{}(", Self::NAME)?;
-        self.to_enum().display_inner(f)?;
-        write!(f, ")")
+        write!(f, "{} {{ ", Self::NAME)?;
+        write!(f, "{}: {}", "source", self.source())?;
+        write!(f, ", {}: {}", "index", self.index())?;
+        write!(f, ", {}: {}", "offset", self.offset())?;
+        write!(f, ", {}: {}", "length", self.length())?;
+        write!(f, " }}")
     }
 }
-impl<'r> ValueReader<'r> {
-    pub const ITEMS_COUNT: usize = 9;
-    pub fn item_id(&self) -> molecule::Number {
-        molecule::unpack_number(self.as_slice())
+impl<'r> RefBytesReader<'r> {
+    pub const TOTAL_SIZE: usize = 20;
+    pub const FIELD_SIZES: [usize; 4] = [8, 4, 4, 4];
+    pub const FIELD_COUNT: usize = 4;
+    pub fn source(&self) -> Uint64Reader<'r> {
+        Uint64Reader::new_unchecked(&self.as_slice()[0..8])
     }
-    pub fn to_enum(&self) -> ValueUnionReader<'r> {
-        let inner = &self.as_slice()[molecule::NUMBER_SIZE..];
-        match self.item_id() {
-            0 => StructReader::new_unchecked(inner).into(),
-            1 => ArrayReader::new_unchecked(inner).into(),
-            2 => BoolReader::new_unchecked(inner).into(),
+    pub fn index(&self) -> Uint32Reader<'r> {
+        Uint32Reader::new_unchecked(&self.as_slice()[8..12])
+    }
+    pub fn offset(&self) -> Uint32Reader<'r> {
+        Uint32Reader::new_unchecked(&self.as_slice()[12..16])
+    }
+    pub fn length(&self) -> Uint32Reader<'r> {
+        Uint32Reader::new_unchecked(&self.as_slice()[16..20])
+    }
+}
+impl<'r> molecule::prelude::Reader<'r> for RefBytesReader<'r> {
+    type Entity = RefBytes;
+    const NAME: &'static str = "RefBytesReader";
+    fn to_entity(&self) -> Self::Entity {
+        Self::Entity::new_unchecked(self.as_slice().to_owned().into())
+    }
+    fn new_unchecked(slice: &'r [u8]) -> Self {
+        RefBytesReader(slice)
+    }
+    fn as_slice(&self) -> &'r [u8] {
+        self.0
+    }
+    fn verify(slice: &[u8], _compatible: bool) -> molecule::error::VerificationResult<()> {
+        use molecule::verification_error as ve;
+        let slice_len = slice.len();
+        if slice_len != Self::TOTAL_SIZE {
+            return ve!(Self, TotalSizeNotMatch, Self::TOTAL_SIZE, slice_len);
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Default)]
+pub struct RefBytesBuilder {
+    pub(crate) source: Uint64,
+    pub(crate) index: Uint32,
+    pub(crate) offset: Uint32,
+    pub(crate) length: Uint32,
+}
+impl RefBytesBuilder {
+    pub const TOTAL_SIZE: usize = 20;
+    pub const FIELD_SIZES: [usize; 4] = [8, 4, 4, 4];
+    pub const FIELD_COUNT: usize = 4;
+    pub fn source(mut self, v: Uint64) -> Self {
+        self.source = v;
+        self
+    }
+    pub fn index(mut self, v: Uint32) -> Self {
+        self.index = v;
+        self
+    }
+    pub fn offset(mut self, v: Uint32) -> Self {
+        self.offset = v;
+        self
+    }
+    pub fn length(mut self, v: Uint32) -> Self {
+        self.length = v;
+        self
+    }
+}
+impl molecule::prelude::Builder for RefBytesBuilder {
+    type Entity = RefBytes;
+    const NAME: &'static str = "RefBytesBuilder";
+    fn expected_length(&self) -> usize {
+        Self::TOTAL_SIZE
+    }
+    fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
+        writer.write_all(self.source.as_slice())?;
+        writer.write_all(self.index.as_slice())?;
+        writer.write_all(self.offset.as_slice())?;
+        writer.write_all(self.length.as_slice())?;
+        Ok(())
+    }
+    fn build(&self) -> Self::Entity {
+        let mut inner = Vec::with_capacity(self.expected_length());
+        self.write(&mut inner)
+            .unwrap_or_else(|_| panic!("{} build should be ok", Self::NAME));
+        RefBytes::new_unchecked(inner.into())
+    }
+}
+#[derive(Clone)]
+pub struct Value(molecule::bytes::Bytes);
+impl ::core::fmt::LowerHex for Value {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl ::core::fmt::Debug for Value {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl ::core::fmt::Display for Value {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}(", Self::NAME)?;
+        self.to_enum().display_inner(f)?;
+        write!(f, ")")
+    }
+}
+impl ::core::default::Default for Value {
+    fn default() -> Self {
+        let v = molecule::bytes::Bytes::from_static(&Self::DEFAULT_VALUE);
+        Value::new_unchecked(v)
+    }
+}
+impl Value {
+    const DEFAULT_VALUE: [u8; 56] = [
+        0, 0, 0, 0, 52, 0, 0, 0, 12, 0, 0, 0, 48, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0,
+    ];
+    pub const ITEMS_COUNT: usize = 10;
+    pub fn item_id(&self) -> molecule::Number {
+        molecule::unpack_number(self.as_slice())
+    }
+    pub fn to_enum(&self) -> ValueUnion {
+        let inner = self.0.slice(molecule::NUMBER_SIZE..);
+        match self.item_id() {
+            0 => Struct::new_unchecked(inner).into(),
+            1 => Array::new_unchecked(inner).into(),
+            2 => Bool::new_unchecked(inner).into(),
+            3 => Bytes::new_unchecked(inner).into(),
+            4 => String::new_unchecked(inner).into(),
+            5 => Address::new_unchecked(inner).into(),
+            6 => FixedBytes::new_unchecked(inner).into(),
+            7 => Int::new_unchecked(inner).into(),
+            8 => Uint::new_unchecked(inner).into(),
+            9 => RefBytes::new_unchecked(inner).into(),
+            _ => panic!("{}: invalid data", Self::NAME),
+        }
+    }
+    pub fn as_reader<'r>(&'r self) -> ValueReader<'r> {
+        ValueReader::new_unchecked(self.as_slice())
+    }
+}
+impl molecule::prelude::Entity for Value {
+    type Builder = ValueBuilder;
+    const NAME: &'static str = "Value";
+    fn new_unchecked(data: molecule::bytes::Bytes) -> Self {
+        Value(data)
+    }
+    fn as_bytes(&self) -> molecule::bytes::Bytes {
+        self.0.clone()
+    }
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+    fn from_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        ValueReader::from_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn from_compatible_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        ValueReader::from_compatible_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn new_builder() -> Self::Builder {
+        ::core::default::Default::default()
+    }
+    fn as_builder(self) -> Self::Builder {
+        Self::new_builder().set(self.to_enum())
+    }
+}
+#[derive(Clone, Copy)]
+pub struct ValueReader<'r>(&'r [u8]);
+impl<'r> ::core::fmt::LowerHex for ValueReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl<'r> ::core::fmt::Debug for ValueReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl<'r> ::core::fmt::Display for ValueReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}(", Self::NAME)?;
+        self.to_enum().display_inner(f)?;
+        write!(f, ")")
+    }
+}
+impl<'r> ValueReader<'r> {
+    pub const ITEMS_COUNT: usize = 10;
+    pub fn item_id(&self) -> molecule::Number {
+        molecule::unpack_number(self.as_slice())
+    }
+    pub fn to_enum(&self) -> ValueUnionReader<'r> {
+        let inner = &self.as_slice()[molecule::NUMBER_SIZE..];
+        match self.item_id() {
+            0 => StructReader::new_unchecked(inner).into(),
+            1 => ArrayReader::new_unchecked(inner).into(),
+            2 => BoolReader::new_unchecked(inner).into(),
             3 => BytesReader::new_unchecked(inner).into(),
             4 => StringReader::new_unchecked(inner).into(),
             5 => AddressReader::new_unchecked(inner).into(),
             6 => FixedBytesReader::new_unchecked(inner).into(),
             7 => IntReader::new_unchecked(inner).into(),
             8 => UintReader::new_unchecked(inner).into(),
+            9 => RefBytesReader::new_unchecked(inner).into(),
             _ => panic!("{}: invalid data", Self::NAME),
         }
     }
@@ -4483,6 +4933,7 @@ impl<'r> molecule::prelude::Reader<'r> for ValueReader<'r> {
             6 => FixedBytesReader::verify(inner_slice, compatible),
             7 => IntReader::verify(inner_slice, compatible),
             8 => UintReader::verify(inner_slice, compatible),
+            9 => RefBytesReader::verify(inner_slice, compatible),
             _ => ve!(Self, UnknownItem, Self::ITEMS_COUNT, item_id),
         }?;
         Ok(())
@@ -4491,7 +4942,7 @@ impl<'r> molecule::prelude::Reader<'r> for ValueReader<'r> {
 #[derive(Debug, Default)]
 pub struct ValueBuilder(pub(crate) ValueUnion);
 impl ValueBuilder {
-    pub const ITEMS_COUNT: usize = 9;
+    pub const ITEMS_COUNT: usize = 10;
     pub fn set<I>(mut self, v: I) -> Self
     where
         I: ::core::convert::Into<ValueUnion>,
@@ -4528,6 +4979,7 @@ pub enum ValueUnion {
     FixedBytes(FixedBytes),
     Int(Int),
     Uint(Uint),
+    RefBytes(RefBytes),
 }
 #[derive(Debug, Clone, Copy)]
 pub enum ValueUnionReader<'r> {
@@ -4540,6 +4992,7 @@ pub enum ValueUnionReader<'r> {
     FixedBytes(FixedBytesReader<'r>),
     Int(IntReader<'r>),
     Uint(UintReader<'r>),
+    RefBytes(RefBytesReader<'r>),
 }
 impl ::core::default::Default for ValueUnion {
     fn default() -> Self {
@@ -4576,6 +5029,9 @@ impl ::core::fmt::Display for ValueUnion {
             ValueUnion::Uint(ref item) => {
                 write!(f, "{}::{}({})", Self::NAME, Uint::NAME, item)
             }
+            ValueUnion::RefBytes(ref item) => {
+                write!(f, "{}::{}({})", Self::NAME, RefBytes::NAME, item)
+            }
         }
     }
 }
@@ -4609,6 +5065,9 @@ impl<'r> ::core::fmt::Display for ValueUnionReader<'r> {
             ValueUnionReader::Uint(ref item) => {
                 write!(f, "{}::{}({})", Self::NAME, Uint::NAME, item)
             }
+            ValueUnionReader::RefBytes(ref item) => {
+                write!(f, "{}::{}({})", Self::NAME, RefBytes::NAME, item)
+            }
         }
     }
 }
@@ -4624,6 +5083,7 @@ impl ValueUnion {
             ValueUnion::FixedBytes(ref item) => write!(f, "{}", item),
             ValueUnion::Int(ref item) => write!(f, "{}", item),
             ValueUnion::Uint(ref item) => write!(f, "{}", item),
+            ValueUnion::RefBytes(ref item) => write!(f, "{}", item),
         }
     }
 }
@@ -4639,6 +5099,7 @@ impl<'r> ValueUnionReader<'r> {
             ValueUnionReader::FixedBytes(ref item) => write!(f, "{}", item),
             ValueUnionReader::Int(ref item) => write!(f, "{}", item),
             ValueUnionReader::Uint(ref item) => write!(f, "{}", item),
+            ValueUnionReader::RefBytes(ref item) => write!(f, "{}", item),
         }
     }
 }
@@ -4687,6 +5148,11 @@ impl ::core::convert::From<Uint> for ValueUnion {
         ValueUnion::Uint(item)
     }
 }
+impl ::core::convert::From<RefBytes> for ValueUnion {
+    fn from(item: RefBytes) -> Self {
+        ValueUnion::RefBytes(item)
+    }
+}
 impl<'r> ::core::convert::From<StructReader<'r>> for ValueUnionReader<'r> {
     fn from(item: StructReader<'r>) -> Self {
         ValueUnionReader::Struct(item)
@@ -4732,6 +5198,11 @@ impl<'r> ::core::convert::From<UintReader<'r>> for ValueUnionReader<'r> {
         ValueUnionReader::Uint(item)
     }
 }
+impl<'r> ::core::convert::From<RefBytesReader<'r>> for ValueUnionReader<'r> {
+    fn from(item: RefBytesReader<'r>) -> Self {
+        ValueUnionReader::RefBytes(item)
+    }
+}
 impl ValueUnion {
     pub const NAME: &'static str = "ValueUnion";
     pub fn as_bytes(&self) -> molecule::bytes::Bytes {
@@ -4745,6 +5216,7 @@ impl ValueUnion {
             ValueUnion::FixedBytes(item) => item.as_bytes(),
             ValueUnion::Int(item) => item.as_bytes(),
             ValueUnion::Uint(item) => item.as_bytes(),
+            ValueUnion::RefBytes(item) => item.as_bytes(),
         }
     }
     pub fn as_slice(&self) -> &[u8] {
@@ -4758,6 +5230,7 @@ impl ValueUnion {
             ValueUnion::FixedBytes(item) => item.as_slice(),
             ValueUnion::Int(item) => item.as_slice(),
             ValueUnion::Uint(item) => item.as_slice(),
+            ValueUnion::RefBytes(item) => item.as_slice(),
         }
     }
     pub fn item_id(&self) -> molecule::Number {
@@ -4771,6 +5244,7 @@ impl ValueUnion {
             ValueUnion::FixedBytes(_) => 6,
             ValueUnion::Int(_) => 7,
             ValueUnion::Uint(_) => 8,
+            ValueUnion::RefBytes(_) => 9,
         }
     }
     pub fn item_name(&self) -> &str {
@@ -4784,6 +5258,7 @@ impl ValueUnion {
             ValueUnion::FixedBytes(_) => "FixedBytes",
             ValueUnion::Int(_) => "Int",
             ValueUnion::Uint(_) => "Uint",
+            ValueUnion::RefBytes(_) => "RefBytes",
         }
     }
     pub fn as_reader<'r>(&'r self) -> ValueUnionReader<'r> {
@@ -4797,6 +5272,7 @@ impl ValueUnion {
             ValueUnion::FixedBytes(item) => item.as_reader().into(),
             ValueUnion::Int(item) => item.as_reader().into(),
             ValueUnion::Uint(item) => item.as_reader().into(),
+            ValueUnion::RefBytes(item) => item.as_reader().into(),
         }
     }
 }
@@ -4813,6 +5289,7 @@ impl<'r> ValueUnionReader<'r> {
             ValueUnionReader::FixedBytes(item) => item.as_slice(),
             ValueUnionReader::Int(item) => item.as_slice(),
             ValueUnionReader::Uint(item) => item.as_slice(),
+            ValueUnionReader::RefBytes(item) => item.as_slice(),
         }
     }
     pub fn item_id(&self) -> molecule::Number {
@@ -4826,6 +5303,7 @@ impl<'r> ValueUnionReader<'r> {
             ValueUnionReader::FixedBytes(_) => 6,
             ValueUnionReader::Int(_) => 7,
             ValueUnionReader::Uint(_) => 8,
+            ValueUnionReader::RefBytes(_) => 9,
         }
     }
     pub fn item_name(&self) -> &str {
@@ -4839,7 +5317,332 @@ impl<'r> ValueUnionReader<'r> {
             ValueUnionReader::FixedBytes(_) => "FixedBytes",
             ValueUnionReader::Int(_) => "Int",
             ValueUnionReader::Uint(_) => "Uint",
+            ValueUnionReader::RefBytes(_) => "RefBytes",
+        }
+    }
+}
+#[derive(Clone)]
+pub struct EIP712Domain(molecule::bytes::Bytes);
+impl ::core::fmt::LowerHex for EIP712Domain {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl ::core::fmt::Debug for EIP712Domain {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl ::core::fmt::Display for EIP712Domain {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} {{ ", Self::NAME)?;
+        write!(f, "{}: {}", "name", self.name())?;
+        write!(f, ", {}: {}", "version", self.version())?;
+        write!(f, ", {}: {}", "chain_id", self.chain_id())?;
+        write!(
+            f,
+            ", {}: {}",
+            "verifying_contract",
+            self.verifying_contract()
+        )?;
+        let extra_count = self.count_extra_fields();
+        if extra_count != 0 {
+            write!(f, ", .. ({} fields)", extra_count)?;
         }
+        write!(f, " }}")
+    }
+}
+impl ::core::default::Default for EIP712Domain {
+    fn default() -> Self {
+        let v = molecule::bytes::Bytes::from_static(&Self::DEFAULT_VALUE);
+        EIP712Domain::new_unchecked(v)
+    }
+}
+impl EIP712Domain {
+    const DEFAULT_VALUE: [u8; 80] = [
+        80, 0, 0, 0, 20, 0, 0, 0, 24, 0, 0, 0, 28, 0, 0, 0, 60, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+    pub const FIELD_COUNT: usize = 4;
+    pub fn total_size(&self) -> usize {
+        molecule::unpack_number(self.as_slice()) as usize
+    }
+    pub fn field_count(&self) -> usize {
+        if self.total_size() == molecule::NUMBER_SIZE {
+            0
+        } else {
+            (molecule::unpack_number(&self.as_slice()[molecule::NUMBER_SIZE..]) as usize / 4) - 1
+        }
+    }
+    pub fn count_extra_fields(&self) -> usize {
+        self.field_count() - Self::FIELD_COUNT
+    }
+    pub fn has_extra_fields(&self) -> bool {
+        Self::FIELD_COUNT != self.field_count()
+    }
+    pub fn name(&self) -> String {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[4..]) as usize;
+        let end = molecule::unpack_number(&slice[8..]) as usize;
+        String::new_unchecked(self.0.slice(start..end))
+    }
+    pub fn version(&self) -> String {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[8..]) as usize;
+        let end = molecule::unpack_number(&slice[12..]) as usize;
+        String::new_unchecked(self.0.slice(start..end))
+    }
+    pub fn chain_id(&self) -> Byte32 {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[12..]) as usize;
+        let end = molecule::unpack_number(&slice[16..]) as usize;
+        Byte32::new_unchecked(self.0.slice(start..end))
+    }
+    pub fn verifying_contract(&self) -> Address {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[16..]) as usize;
+        if self.has_extra_fields() {
+            let end = molecule::unpack_number(&slice[20..]) as usize;
+            Address::new_unchecked(self.0.slice(start..end))
+        } else {
+            Address::new_unchecked(self.0.slice(start..))
+        }
+    }
+    pub fn as_reader<'r>(&'r self) -> EIP712DomainReader<'r> {
+        EIP712DomainReader::new_unchecked(self.as_slice())
+    }
+}
+impl molecule::prelude::Entity for EIP712Domain {
+    type Builder = EIP712DomainBuilder;
+    const NAME: &'static str = "EIP712Domain";
+    fn new_unchecked(data: molecule::bytes::Bytes) -> Self {
+        EIP712Domain(data)
+    }
+    fn as_bytes(&self) -> molecule::bytes::Bytes {
+        self.0.clone()
+    }
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+    fn from_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        EIP712DomainReader::from_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn from_compatible_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        EIP712DomainReader::from_compatible_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn new_builder() -> Self::Builder {
+        ::core::default::Default::default()
+    }
+    fn as_builder(self) -> Self::Builder {
+        Self::new_builder()
+            .name(self.name())
+            .version(self.version())
+            .chain_id(self.chain_id())
+            .verifying_contract(self.verifying_contract())
+    }
+}
+#[derive(Clone, Copy)]
+pub struct EIP712DomainReader<'r>(&'r [u8]);
+impl<'r> ::core::fmt::LowerHex for EIP712DomainReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl<'r> ::core::fmt::Debug for EIP712DomainReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl<'r> ::core::fmt::Display for EIP712DomainReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} {{ ", Self::NAME)?;
+        write!(f, "{}: {}", "name", self.name())?;
+        write!(f, ", {}: {}", "version", self.version())?;
+        write!(f, ", {}: {}", "chain_id", self.chain_id())?;
+        write!(
+            f,
+            ", {}: {}",
+            "verifying_contract",
+            self.verifying_contract()
+        )?;
+        let extra_count = self.count_extra_fields();
+        if extra_count != 0 {
+            write!(f, ", .. ({} fields)", extra_count)?;
+        }
+        write!(f, " }}")
+    }
+}
+impl<'r> EIP712DomainReader<'r> {
+    pub const FIELD_COUNT: usize = 4;
+    pub fn total_size(&self) -> usize {
+        molecule::unpack_number(self.as_slice()) as usize
+    }
+    pub fn field_count(&self) -> usize {
+        if self.total_size() == molecule::NUMBER_SIZE {
+            0
+        } else {
+            (molecule::unpack_number(&self.as_slice()[molecule::NUMBER_SIZE..]) as usize / 4) - 1
+        }
+    }
+    pub fn count_extra_fields(&self) -> usize {
+        self.field_count() - Self::FIELD_COUNT
+    }
+    pub fn has_extra_fields(&self) -> bool {
+        Self::FIELD_COUNT != self.field_count()
+    }
+    pub fn name(&self) -> StringReader<'r> {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[4..]) as usize;
+        let end = molecule::unpack_number(&slice[8..]) as usize;
+        StringReader::new_unchecked(&self.as_slice()[start..end])
+    }
+    pub fn version(&self) -> StringReader<'r> {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[8..]) as usize;
+        let end = molecule::unpack_number(&slice[12..]) as usize;
+        StringReader::new_unchecked(&self.as_slice()[start..end])
+    }
+    pub fn chain_id(&self) -> Byte32Reader<'r> {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[12..]) as usize;
+        let end = molecule::unpack_number(&slice[16..]) as usize;
+        Byte32Reader::new_unchecked(&self.as_slice()[start..end])
+    }
+    pub fn verifying_contract(&self) -> AddressReader<'r> {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[16..]) as usize;
+        if self.has_extra_fields() {
+            let end = molecule::unpack_number(&slice[20..]) as usize;
+            AddressReader::new_unchecked(&self.as_slice()[start..end])
+        } else {
+            AddressReader::new_unchecked(&self.as_slice()[start..])
+        }
+    }
+}
+impl<'r> molecule::prelude::Reader<'r> for EIP712DomainReader<'r> {
+    type Entity = EIP712Domain;
+    const NAME: &'static str = "EIP712DomainReader";
+    fn to_entity(&self) -> Self::Entity {
+        Self::Entity::new_unchecked(self.as_slice().to_owned().into())
+    }
+    fn new_unchecked(slice: &'r [u8]) -> Self {
+        EIP712DomainReader(slice)
+    }
+    fn as_slice(&self) -> &'r [u8] {
+        self.0
+    }
+    fn verify(slice: &[u8], compatible: bool) -> molecule::error::VerificationResult<()> {
+        use molecule::verification_error as ve;
+        let slice_len = slice.len();
+        if slice_len < molecule::NUMBER_SIZE {
+            return ve!(Self, HeaderIsBroken, molecule::NUMBER_SIZE, slice_len);
+        }
+        let total_size = molecule::unpack_number(slice) as usize;
+        if slice_len != total_size {
+            return ve!(Self, TotalSizeNotMatch, total_size, slice_len);
+        }
+        if slice_len < molecule::NUMBER_SIZE * 2 {
+            return ve!(Self, HeaderIsBroken, molecule::NUMBER_SIZE * 2, slice_len);
+        }
+        let offset_first = molecule::unpack_number(&slice[molecule::NUMBER_SIZE..]) as usize;
+        if offset_first % molecule::NUMBER_SIZE != 0 || offset_first < molecule::NUMBER_SIZE * 2 {
+            return ve!(Self, OffsetsNotMatch);
+        }
+        if slice_len < offset_first {
+            return ve!(Self, HeaderIsBroken, offset_first, slice_len);
+        }
+        let field_count = offset_first / molecule::NUMBER_SIZE - 1;
+        if field_count < Self::FIELD_COUNT {
+            return ve!(Self, FieldCountNotMatch, Self::FIELD_COUNT, field_count);
+        } else if !compatible && field_count > Self::FIELD_COUNT {
+            return ve!(Self, FieldCountNotMatch, Self::FIELD_COUNT, field_count);
+        };
+        let mut offsets: Vec<usize> = slice[molecule::NUMBER_SIZE..offset_first]
+            .chunks_exact(molecule::NUMBER_SIZE)
+            .map(|x| molecule::unpack_number(x) as usize)
+            .collect();
+        offsets.push(total_size);
+        if offsets.windows(2).any(|i| i[0] > i[1]) {
+            return ve!(Self, OffsetsNotMatch);
+        }
+        StringReader::verify(&slice[offsets[0]..offsets[1]], compatible)?;
+        StringReader::verify(&slice[offsets[1]..offsets[2]], compatible)?;
+        Byte32Reader::verify(&slice[offsets[2]..offsets[3]], compatible)?;
+        AddressReader::verify(&slice[offsets[3]..offsets[4]], compatible)?;
+        Ok(())
+    }
+}
+#[derive(Debug, Default)]
+pub struct EIP712DomainBuilder {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) chain_id: Byte32,
+    pub(crate) verifying_contract: Address,
+}
+impl EIP712DomainBuilder {
+    pub const FIELD_COUNT: usize = 4;
+    pub fn name(mut self, v: String) -> Self {
+        self.name = v;
+        self
+    }
+    pub fn version(mut self, v: String) -> Self {
+        self.version = v;
+        self
+    }
+    pub fn chain_id(mut self, v: Byte32) -> Self {
+        self.chain_id = v;
+        self
+    }
+    pub fn verifying_contract(mut self, v: Address) -> Self {
+        self.verifying_contract = v;
+        self
+    }
+}
+impl molecule::prelude::Builder for EIP712DomainBuilder {
+    type Entity = EIP712Domain;
+    const NAME: &'static str = "EIP712DomainBuilder";
+    fn expected_length(&self) -> usize {
+        molecule::NUMBER_SIZE * (Self::FIELD_COUNT + 1)
+            + self.name.as_slice().len()
+            + self.version.as_slice().len()
+            + self.chain_id.as_slice().len()
+            + self.verifying_contract.as_slice().len()
+    }
+    fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
+        let mut total_size = molecule::NUMBER_SIZE * (Self::FIELD_COUNT + 1);
+        let mut offsets = Vec::with_capacity(Self::FIELD_COUNT);
+        offsets.push(total_size);
+        total_size += self.name.as_slice().len();
+        offsets.push(total_size);
+        total_size += self.version.as_slice().len();
+        offsets.push(total_size);
+        total_size += self.chain_id.as_slice().len();
+        offsets.push(total_size);
+        total_size += self.verifying_contract.as_slice().len();
+        writer.write_all(&molecule::pack_number(total_size as molecule::Number))?;
+        for offset in offsets.into_iter() {
+            writer.write_all(&molecule::pack_number(offset as molecule::Number))?;
+        }
+        writer.write_all(self.name.as_slice())?;
+        writer.write_all(self.version.as_slice())?;
+        writer.write_all(self.chain_id.as_slice())?;
+        writer.write_all(self.verifying_contract.as_slice())?;
+        Ok(())
+    }
+    fn build(&self) -> Self::Entity {
+        let mut inner = Vec::with_capacity(self.expected_length());
+        self.write(&mut inner)
+            .unwrap_or_else(|_| panic!("{} build should be ok", Self::NAME));
+        EIP712Domain::new_unchecked(inner.into())
     }
 }
 #[derive(Clone)]
@@ -1,2 +1,5 @@
+// Generated by moleculec, see .github/workflows/rust.yml
+#[allow(clippy::all)]
 pub mod basic;
+#[allow(clippy::all)]
 pub mod top_level;
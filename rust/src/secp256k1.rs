@@ -0,0 +1,312 @@
+//! Signature verification for secp256k1 based locks. Signatures are 65-byte
+//! recoverable signatures(r, s, then recovery id) over the sighash-all
+//! message hash. Like CKB's default lock, a signer is identified by its
+//! blake160: the first 20 bytes of blake2b hash on the compressed public key.
+//! Ethereum style signatures are also supported, where a signer is
+//! identified by its Ethereum address instead.
+
+use crate::{
+    ct_eq, eip712::build_typed_message_hash, fetch_sighash_with_action, generate_sighash_all_hash,
+    load_group_lock, new_blake2b, syscalls::load_script, Error,
+};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+
+pub const SIGNATURE_SIZE: usize = 65;
+
+/// The canonical flow of a secp256k1 blake160 lock: the lock field of
+/// current script group's first witness must be a 65-byte signature over
+/// sighash-all message hash, signed by the key whose blake160 is kept in
+/// current script's args. This works for both Sighash and SighashWithAction
+/// witnesses, the action, if any, is covered by sighash-all message hash.
+///
+/// Errors are granular for diagnosing: `InvalidSealLength` for seals not of
+/// 65 bytes, `ScriptArgsLength` for args not of 20 bytes, and
+/// `SignatureVerification` when the signature cannot be recovered or belongs
+/// to another key.
+pub fn default_secp256k1_verify() -> Result<(), Error> {
+    let script = load_script()?;
+    let args = script.as_reader().args().raw_data();
+    if args.len() != 20 {
+        return Err(Error::ScriptArgsLength);
+    }
+    let seal = load_group_lock()?;
+    if seal.len() != SIGNATURE_SIZE {
+        return Err(Error::InvalidSealLength);
+    }
+    let message = generate_sighash_all_hash()?;
+    match recover_blake160(&message, &seal) {
+        Some(signer) if ct_eq(&signer, args) => Ok(()),
+        _signer => {
+            #[cfg(feature = "debug")]
+            {
+                crate::debug_hex("expected signer", args);
+                if let Some(signer) = _signer {
+                    crate::debug_hex("recovered signer", &signer);
+                }
+            }
+            Err(Error::SignatureVerification)
+        }
+    }
+}
+
+/// Verifies a 2-of-2 co-signed transaction, where the lock field of current
+/// script group's first witness holds 2 signatures concatenated. One of them
+/// must be signed by `signer_a`, the other by `signer_b`, in any order. The
+/// two signers must be distinct.
+pub fn verify_dual_signers(signer_a: &[u8; 20], signer_b: &[u8; 20]) -> Result<bool, Error> {
+    if ct_eq(signer_a, signer_b) {
+        return Ok(false);
+    }
+    let seal = load_group_lock()?;
+    if seal.len() != SIGNATURE_SIZE * 2 {
+        return Ok(false);
+    }
+    let message = generate_sighash_all_hash()?;
+    let (first, second) = match (
+        recover_blake160(&message, &seal[..SIGNATURE_SIZE]),
+        recover_blake160(&message, &seal[SIGNATURE_SIZE..]),
+    ) {
+        (Some(first), Some(second)) => (first, second),
+        _ => return Ok(false),
+    };
+    // Both orders are always compared, so timing does not tell which one
+    // matched
+    let in_order = ct_eq(&first, signer_a) & ct_eq(&second, signer_b);
+    let swapped = ct_eq(&first, signer_b) & ct_eq(&second, signer_a);
+    Ok(in_order | swapped)
+}
+
+/// Verifies the lock field of current script group's first witness as an
+/// Ethereum `personal_sign` signature over EIP-712 hash of the action
+/// message, for wallets that cannot do `eth_signTypedData`. The signed
+/// message is keccak256 of `"\x19Ethereum Signed Message:\n32"` followed by
+/// EIP-712 hash. Both 0/1 and 27/28 are accepted as recovery id.
+///
+/// Note EIP-712 hash only commits to the action message, not to current
+/// transaction, the script must bind the message to current transaction by
+/// other means.
+pub fn verify_personal_sign_eip712(expected_signer: &[u8; 20]) -> Result<bool, Error> {
+    let sighash_with_action = fetch_sighash_with_action()?;
+    let eip712_hash = build_typed_message_hash(&sighash_with_action.message().as_reader())?;
+    let mut hasher = Keccak256::default();
+    hasher.update(b"\x19Ethereum Signed Message:\n32");
+    hasher.update(eip712_hash);
+    let mut message = [0u8; 32];
+    message.copy_from_slice(&hasher.finalize());
+
+    let mut seal = load_group_lock()?;
+    if seal.len() != SIGNATURE_SIZE {
+        return Ok(false);
+    }
+    if seal[64] >= 27 {
+        seal[64] -= 27;
+    }
+    Ok(recover_eth_address(&message, &seal).is_some_and(|signer| ct_eq(&signer, expected_signer)))
+}
+
+fn recover_eth_address(message: &[u8; 32], signature: &[u8]) -> Option<[u8; 20]> {
+    let key = recover(message, signature)?;
+    // Ethereum address is the last 20 bytes of keccak256 hash on the
+    // uncompressed public key, excluding the 0x04 prefix
+    let hash = Keccak256::digest(&key.to_encoded_point(false).as_bytes()[1..]);
+    let mut result = [0u8; 20];
+    result.copy_from_slice(&hash[12..]);
+    Some(result)
+}
+
+fn recover_blake160(message: &[u8; 32], signature: &[u8]) -> Option<[u8; 20]> {
+    let key = recover(message, signature)?;
+    let mut hasher = new_blake2b();
+    hasher.update(key.to_encoded_point(true).as_bytes());
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    let mut result = [0u8; 20];
+    result.copy_from_slice(&hash[..20]);
+    Some(result)
+}
+
+fn recover(message: &[u8; 32], signature: &[u8]) -> Option<VerifyingKey> {
+    if signature.len() != SIGNATURE_SIZE {
+        return None;
+    }
+    let recovery_id = RecoveryId::from_byte(signature[64])?;
+    let signature = Signature::from_slice(&signature[..64]).ok()?;
+    VerifyingKey::recover_from_prehash(message, &signature, recovery_id).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::{build_sighash_witness, SighashWithActionBuilder},
+        schemas::basic::SighashWithAction,
+        MockTransaction,
+    };
+    use alloc::{vec, vec::Vec};
+    use ckb_std::ckb_types::{packed::Script, prelude::*};
+    use k256::ecdsa::SigningKey;
+    use molecule::prelude::{Builder, Entity};
+
+    fn signing_key(n: u8) -> SigningKey {
+        SigningKey::from_slice(&[n; 32]).unwrap()
+    }
+
+    fn blake160(key: &SigningKey) -> [u8; 20] {
+        let mut hasher = new_blake2b();
+        hasher.update(key.verifying_key().to_encoded_point(true).as_bytes());
+        let mut hash = [0u8; 32];
+        hasher.finalize(&mut hash);
+        hash[..20].try_into().unwrap()
+    }
+
+    fn install(args: &[u8], seal: &[u8]) {
+        let witness = build_sighash_witness(seal);
+        MockTransaction {
+            tx_hash: [2u8; 32],
+            input_count: 1,
+            group_inputs: vec![0],
+            witnesses: vec![witness.as_slice().to_vec()],
+            script: Script::new_builder()
+                .args(args.pack())
+                .build()
+                .as_slice()
+                .to_vec(),
+            ..Default::default()
+        }
+        .install();
+    }
+
+    // Signs sighash-all message hash of the transaction built by `install`,
+    // which does not depend on the seal
+    fn sign(key: &SigningKey) -> Vec<u8> {
+        install(&[], &[]);
+        let message = generate_sighash_all_hash().unwrap();
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&message).unwrap();
+        let mut seal = signature.to_bytes().to_vec();
+        seal.push(recovery_id.to_byte());
+        seal
+    }
+
+    #[test]
+    fn test_default_verify() {
+        let key = signing_key(1);
+        let seal = sign(&key);
+        install(&blake160(&key), &seal);
+        assert_eq!(default_secp256k1_verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_default_verify_other_signer() {
+        let seal = sign(&signing_key(1));
+        install(&blake160(&signing_key(2)), &seal);
+        assert_eq!(
+            default_secp256k1_verify(),
+            Err(Error::SignatureVerification)
+        );
+    }
+
+    #[test]
+    fn test_default_verify_malformed_recovery_id() {
+        let key = signing_key(1);
+        let mut seal = sign(&key);
+        seal[64] = 4;
+        install(&blake160(&key), &seal);
+        assert_eq!(
+            default_secp256k1_verify(),
+            Err(Error::SignatureVerification)
+        );
+    }
+
+    #[test]
+    fn test_default_verify_lengths() {
+        let key = signing_key(1);
+        let seal = sign(&key);
+        install(&blake160(&key)[..19], &seal);
+        assert_eq!(default_secp256k1_verify(), Err(Error::ScriptArgsLength));
+        install(&blake160(&key), &seal[..64]);
+        assert_eq!(default_secp256k1_verify(), Err(Error::InvalidSealLength));
+    }
+
+    #[test]
+    fn test_dual_signers() {
+        let (a, b, c) = (signing_key(1), signing_key(2), signing_key(3));
+        let seal = [sign(&a), sign(&b)].concat();
+        install(&[], &seal);
+
+        assert_eq!(verify_dual_signers(&blake160(&a), &blake160(&b)), Ok(true));
+        assert_eq!(verify_dual_signers(&blake160(&b), &blake160(&a)), Ok(true));
+        assert_eq!(verify_dual_signers(&blake160(&a), &blake160(&c)), Ok(false));
+        assert_eq!(verify_dual_signers(&blake160(&c), &blake160(&b)), Ok(false));
+    }
+
+    #[test]
+    fn test_dual_signers_swapped_seal() {
+        let (a, b) = (signing_key(1), signing_key(2));
+        let seal = [sign(&b), sign(&a)].concat();
+        install(&[], &seal);
+
+        assert_eq!(verify_dual_signers(&blake160(&a), &blake160(&b)), Ok(true));
+    }
+
+    #[test]
+    fn test_dual_signers_same_signer() {
+        let a = signing_key(1);
+        let seal = [sign(&a), sign(&a)].concat();
+        install(&[], &seal);
+
+        assert_eq!(verify_dual_signers(&blake160(&a), &blake160(&a)), Ok(false));
+        assert_eq!(
+            verify_dual_signers(&blake160(&a), &blake160(&signing_key(2))),
+            Ok(false)
+        );
+    }
+
+    fn install_action(seal: &[u8]) {
+        let witness = SighashWithActionBuilder::new()
+            .set_seal(seal)
+            .build_witness();
+        MockTransaction {
+            input_count: 1,
+            group_inputs: vec![0],
+            witnesses: vec![witness.as_slice().to_vec()],
+            ..Default::default()
+        }
+        .install();
+    }
+
+    #[test]
+    fn test_personal_sign_eip712() {
+        // Private key and address from ethers' documentation
+        let secret = [
+            0x01, 0x23, 0x45, 0x67, 0x89, 0x01, 0x23, 0x45, 0x67, 0x89, 0x01, 0x23, 0x45, 0x67,
+            0x89, 0x01, 0x23, 0x45, 0x67, 0x89, 0x01, 0x23, 0x45, 0x67, 0x89, 0x01, 0x23, 0x45,
+            0x67, 0x89, 0x01, 0x23,
+        ];
+        let key = SigningKey::from_slice(&secret).unwrap();
+        let address: [u8; 20] = [
+            0x14, 0x79, 0x16, 0x97, 0x26, 0x0e, 0x4c, 0x9a, 0x71, 0xf1, 0x84, 0x84, 0xc9, 0xf9,
+            0x97, 0xb3, 0x08, 0xe5, 0x93, 0x25,
+        ];
+
+        let eip712_hash =
+            build_typed_message_hash(&SighashWithAction::default().message().as_reader()).unwrap();
+        let mut hasher = Keccak256::default();
+        hasher.update(b"\x19Ethereum Signed Message:\n32");
+        hasher.update(eip712_hash);
+        let message = hasher.finalize();
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&message).unwrap();
+        let mut seal = signature.to_bytes().to_vec();
+        seal.push(recovery_id.to_byte() + 27);
+
+        install_action(&seal);
+        assert_eq!(verify_personal_sign_eip712(&address), Ok(true));
+        assert_eq!(verify_personal_sign_eip712(&[0u8; 20]), Ok(false));
+
+        seal[64] -= 27;
+        install_action(&seal);
+        assert_eq!(verify_personal_sign_eip712(&address), Ok(true));
+        install_action(&seal[..64]);
+        assert_eq!(verify_personal_sign_eip712(&address), Ok(false));
+    }
+}
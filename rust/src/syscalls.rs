@@ -0,0 +1,403 @@
+//! Wrappers over the syscalls used to generate sighash-all message hash,
+//! and to resolve hashes referenced in typed messages.
+//! With `profile` feature enabled, each call is counted so integrators can
+//! measure the cost of a particular transaction shape.
+//!
+//! With `mock-syscalls` feature enabled, the wrappers read from an in-memory
+//! `MockTransaction` instead of issuing real syscalls, so the hashing logic
+//! can be exercised off-chain. Only syscalls routed through this module are
+//! mocked, other APIs still talk to CKB-VM.
+
+use alloc::vec::Vec;
+#[cfg(any(feature = "secp256k1", feature = "mock-syscalls"))]
+use ckb_std::ckb_types::packed::Script;
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::packed::{CellInput, CellOutput, Transaction},
+    error::SysError,
+};
+#[cfg(not(feature = "mock-syscalls"))]
+use ckb_std::{high_level, syscalls};
+#[cfg(feature = "profile")]
+use core::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "mock-syscalls")]
+use molecule::prelude::Entity;
+
+#[cfg(all(feature = "profile", not(feature = "mock-syscalls")))]
+static SYSCALLS: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(all(feature = "profile", not(feature = "mock-syscalls")))]
+fn with_counter<R>(f: impl FnOnce(&AtomicU64) -> R) -> R {
+    f(&SYSCALLS)
+}
+
+// Like MockTransaction, the counter is kept per thread, so tests running in
+// parallel do not count each other's syscalls
+#[cfg(all(feature = "profile", feature = "mock-syscalls"))]
+std::thread_local! {
+    static SYSCALLS: AtomicU64 = const { AtomicU64::new(0) };
+}
+
+#[cfg(all(feature = "profile", feature = "mock-syscalls"))]
+fn with_counter<R>(f: impl FnOnce(&AtomicU64) -> R) -> R {
+    SYSCALLS.with(f)
+}
+
+#[cfg(feature = "profile")]
+pub(crate) fn reset_syscall_count() {
+    with_counter(|c| c.store(0, Ordering::Relaxed));
+}
+
+#[cfg(feature = "profile")]
+pub(crate) fn syscall_count() -> u64 {
+    with_counter(|c| c.load(Ordering::Relaxed))
+}
+
+#[inline]
+fn count() {
+    #[cfg(feature = "profile")]
+    with_counter(|c| c.fetch_add(1, Ordering::Relaxed));
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_tx_hash() -> Result<[u8; 32], SysError> {
+    count();
+    high_level::load_tx_hash()
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_witness(index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+    count();
+    high_level::load_witness(index, source)
+}
+
+// Length of a witness, obtained without copying the witness itself
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_witness_len(index: usize, source: Source) -> Result<usize, SysError> {
+    count();
+    match syscalls::load_witness(&mut [], 0, index, source) {
+        Ok(len) | Err(SysError::LengthNotEnough(len)) => Ok(len),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_input_since(index: usize, source: Source) -> Result<u64, SysError> {
+    count();
+    high_level::load_input_since(index, source)
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_cell_data(
+    buf: &mut [u8],
+    offset: usize,
+    index: usize,
+    source: Source,
+) -> Result<usize, SysError> {
+    count();
+    syscalls::load_cell_data(buf, offset, index, source)
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_full_cell_data(index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+    count();
+    high_level::load_cell_data(index, source)
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_transaction(buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+    count();
+    syscalls::load_transaction(buf, offset)
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_full_transaction() -> Result<Transaction, SysError> {
+    count();
+    high_level::load_transaction()
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_input(index: usize, source: Source) -> Result<CellInput, SysError> {
+    count();
+    high_level::load_input(index, source)
+}
+
+#[cfg(all(feature = "secp256k1", not(feature = "mock-syscalls")))]
+pub(crate) fn load_script() -> Result<Script, SysError> {
+    count();
+    high_level::load_script()
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_script_hash() -> Result<[u8; 32], SysError> {
+    count();
+    high_level::load_script_hash()
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_cell(index: usize, source: Source) -> Result<CellOutput, SysError> {
+    count();
+    high_level::load_cell(index, source)
+}
+
+#[cfg(not(feature = "mock-syscalls"))]
+pub(crate) fn load_cell_lock_hash(index: usize, source: Source) -> Result<[u8; 32], SysError> {
+    count();
+    high_level::load_cell_lock_hash(index, source)
+}
+
+/// In-memory transaction backing the syscall wrappers when `mock-syscalls`
+/// feature is enabled. Witnesses and input cells are available via
+/// `Source::Input` and `Source::GroupInput`, output cells via
+/// `Source::Output`, other sources yield `ItemMissing`. CellInputs, output
+/// cells and output data are read from `transaction`.
+///
+/// ```
+/// use blake2b_ref::Blake2bBuilder;
+/// use ckb_typed_message_signing::{
+///     generate_sighash_all_hash,
+///     schemas::{basic::Sighash, top_level::ExtendedWitness},
+///     MockTransaction, SIGHASH_TAG,
+/// };
+/// use molecule::prelude::{Builder, Entity};
+///
+/// let witness = ExtendedWitness::new_builder().set(Sighash::default()).build();
+/// MockTransaction {
+///     tx_hash: [7u8; 32],
+///     input_count: 1,
+///     group_inputs: vec![0],
+///     witnesses: vec![witness.as_slice().to_vec()],
+///     ..Default::default()
+/// }
+/// .install();
+///
+/// let mut expected = [0u8; 32];
+/// let mut hasher = Blake2bBuilder::new(32).personal(b"ckb-default-hash").build();
+/// hasher.update(&[7u8; 32]);
+/// hasher.update(&[SIGHASH_TAG]);
+/// hasher.finalize(&mut expected);
+/// assert_eq!(generate_sighash_all_hash().unwrap(), expected);
+/// ```
+#[cfg(feature = "mock-syscalls")]
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct MockTransaction {
+    pub tx_hash: [u8; 32],
+    /// Number of input cells
+    pub input_count: usize,
+    /// Since value of each input cell, missing ones default to 0
+    pub input_since: Vec<u64>,
+    /// Indices of input cells belonging to current script group
+    pub group_inputs: Vec<usize>,
+    /// All witnesses of the transaction, in order
+    pub witnesses: Vec<Vec<u8>>,
+    /// Data of each input cell, missing ones default to empty
+    pub input_cell_data: Vec<Vec<u8>>,
+    /// Molecule serialized CellOutput of each input cell, missing ones
+    /// default to an empty CellOutput
+    pub input_cells: Vec<Vec<u8>>,
+    /// Molecule serialized transaction, an empty one stands for the default
+    /// Transaction when loaded in full
+    pub transaction: Vec<u8>,
+    /// Molecule serialized script being executed, an empty one stands for
+    /// the default Script
+    pub script: Vec<u8>,
+}
+
+#[cfg(feature = "mock-syscalls")]
+std::thread_local! {
+    static MOCK_TRANSACTION: core::cell::RefCell<MockTransaction> =
+        core::cell::RefCell::new(MockTransaction::default());
+}
+
+#[cfg(feature = "mock-syscalls")]
+impl MockTransaction {
+    /// Makes this transaction the one seen by syscalls on current thread
+    pub fn install(self) {
+        MOCK_TRANSACTION.with(|tx| *tx.borrow_mut() = self);
+    }
+
+    // Translates an index in `source` to a global input index
+    fn resolve(&self, index: usize, source: Source) -> Result<usize, SysError> {
+        let global = match source {
+            Source::Input => index,
+            Source::GroupInput => *self
+                .group_inputs
+                .get(index)
+                .ok_or(SysError::IndexOutOfBound)?,
+            _ => return Err(SysError::ItemMissing),
+        };
+        Ok(global)
+    }
+
+    // Translates an index in `source` to a global input index, which must
+    // be below `input_count`
+    fn resolve_input(&self, index: usize, source: Source) -> Result<usize, SysError> {
+        let i = self.resolve(index, source)?;
+        if i >= self.input_count {
+            return Err(SysError::IndexOutOfBound);
+        }
+        Ok(i)
+    }
+
+    fn full_transaction(&self) -> Result<Transaction, SysError> {
+        if self.transaction.is_empty() {
+            return Ok(Transaction::default());
+        }
+        Transaction::from_slice(&self.transaction).map_err(|_| SysError::Encoding)
+    }
+
+    fn cell_data(&self, index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+        if source == Source::Output {
+            let data = self.full_transaction()?.raw().outputs_data().get(index);
+            return Ok(data.ok_or(SysError::IndexOutOfBound)?.raw_data().to_vec());
+        }
+        let i = self.resolve_input(index, source)?;
+        Ok(self.input_cell_data.get(i).cloned().unwrap_or_default())
+    }
+
+    fn script(&self) -> Result<Script, SysError> {
+        if self.script.is_empty() {
+            return Ok(Script::default());
+        }
+        Script::from_slice(&self.script).map_err(|_| SysError::Encoding)
+    }
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_tx_hash() -> Result<[u8; 32], SysError> {
+    count();
+    Ok(MOCK_TRANSACTION.with(|tx| tx.borrow().tx_hash))
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_witness(index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+    count();
+    MOCK_TRANSACTION.with(|tx| {
+        let tx = tx.borrow();
+        let i = tx.resolve(index, source)?;
+        tx.witnesses
+            .get(i)
+            .cloned()
+            .ok_or(SysError::IndexOutOfBound)
+    })
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_witness_len(index: usize, source: Source) -> Result<usize, SysError> {
+    count();
+    MOCK_TRANSACTION.with(|tx| {
+        let tx = tx.borrow();
+        let i = tx.resolve(index, source)?;
+        tx.witnesses
+            .get(i)
+            .map(|w| w.len())
+            .ok_or(SysError::IndexOutOfBound)
+    })
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_input_since(index: usize, source: Source) -> Result<u64, SysError> {
+    count();
+    MOCK_TRANSACTION.with(|tx| {
+        let tx = tx.borrow();
+        let i = tx.resolve_input(index, source)?;
+        Ok(tx.input_since.get(i).copied().unwrap_or(0))
+    })
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_cell_data(
+    buf: &mut [u8],
+    offset: usize,
+    index: usize,
+    source: Source,
+) -> Result<usize, SysError> {
+    count();
+    MOCK_TRANSACTION.with(|tx| {
+        let data = tx.borrow().cell_data(index, source)?;
+        load_partial(buf, &data, offset)
+    })
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_full_cell_data(index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+    count();
+    MOCK_TRANSACTION.with(|tx| tx.borrow().cell_data(index, source))
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_transaction(buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+    count();
+    MOCK_TRANSACTION.with(|tx| load_partial(buf, &tx.borrow().transaction, offset))
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_full_transaction() -> Result<Transaction, SysError> {
+    count();
+    MOCK_TRANSACTION.with(|tx| tx.borrow().full_transaction())
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_input(index: usize, source: Source) -> Result<CellInput, SysError> {
+    count();
+    MOCK_TRANSACTION.with(|tx| {
+        let tx = tx.borrow();
+        let i = tx.resolve(index, source)?;
+        let inputs = tx.full_transaction()?.raw().inputs();
+        inputs.get(i).ok_or(SysError::IndexOutOfBound)
+    })
+}
+
+#[cfg(all(feature = "secp256k1", feature = "mock-syscalls"))]
+pub(crate) fn load_script() -> Result<Script, SysError> {
+    count();
+    MOCK_TRANSACTION.with(|tx| tx.borrow().script())
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_script_hash() -> Result<[u8; 32], SysError> {
+    count();
+    let script = MOCK_TRANSACTION.with(|tx| tx.borrow().script())?;
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(script.calc_script_hash().as_slice());
+    Ok(hash)
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_cell(index: usize, source: Source) -> Result<CellOutput, SysError> {
+    count();
+    MOCK_TRANSACTION.with(|tx| {
+        let tx = tx.borrow();
+        if source == Source::Output {
+            let outputs = tx.full_transaction()?.raw().outputs();
+            return outputs.get(index).ok_or(SysError::IndexOutOfBound);
+        }
+        let i = tx.resolve_input(index, source)?;
+        match tx.input_cells.get(i) {
+            Some(cell) => CellOutput::from_slice(cell).map_err(|_| SysError::Encoding),
+            None => Ok(CellOutput::default()),
+        }
+    })
+}
+
+#[cfg(feature = "mock-syscalls")]
+pub(crate) fn load_cell_lock_hash(index: usize, source: Source) -> Result<[u8; 32], SysError> {
+    let cell = load_cell(index, source)?;
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(cell.lock().calc_script_hash().as_slice());
+    Ok(hash)
+}
+
+// Mirrors CKB's partial loading: `offset` is clamped to the data length,
+// `LengthNotEnough` carries the full remaining length when `buf` is short.
+#[cfg(feature = "mock-syscalls")]
+fn load_partial(buf: &mut [u8], data: &[u8], offset: usize) -> Result<usize, SysError> {
+    let remaining = &data[core::cmp::min(offset, data.len())..];
+    let n = core::cmp::min(buf.len(), remaining.len());
+    buf[..n].copy_from_slice(&remaining[..n]);
+    if remaining.len() > buf.len() {
+        Err(SysError::LengthNotEnough(remaining.len()))
+    } else {
+        Ok(remaining.len())
+    }
+}
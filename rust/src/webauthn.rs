@@ -0,0 +1,216 @@
+//! WebAuthn(passkey) support. A passkey cannot sign arbitrary messages,
+//! instead it signs over authenticator data and client data JSON, where the
+//! challenge embedded in client data JSON is provided by the relying party.
+//! Here the challenge is the sighash-all message hash of current transaction.
+
+use crate::{ct_eq, generate_sighash_all_hash, load_group_lock, Error};
+use alloc::vec::Vec;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+const CHALLENGE_KEY: &[u8] = b"\"challenge\":\"";
+const GET_TYPE: &[u8] = b"\"type\":\"webauthn.get\"";
+// User present bit in the flags byte of authenticator data, which follows
+// the 32-byte RP ID hash
+const FLAGS_OFFSET: usize = 32;
+const FLAG_USER_PRESENT: u8 = 0x01;
+const BASE64URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Verifies a WebAuthn assertion for current script group. The lock field
+/// of the group's first witness is treated as a 64-byte P-256 signature(r
+/// followed by s), `pubkey` is the uncompressed public key without the 0x04
+/// prefix. Client data JSON must contain the base64url encoded(no padding)
+/// sighash-all message hash as its challenge, in the compact
+/// `"challenge":"..."` form browsers generate. It must also be of
+/// `"type":"webauthn.get"`, so an assertion is never confused with a
+/// credential creation, and the user present flag must be set in
+/// authenticator data.
+///
+/// Like `generate_sighash_all_hash`, this requires current transaction to be
+/// a typed transaction.
+pub fn verify_webauthn(
+    pubkey: &[u8; 64],
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+) -> Result<bool, Error> {
+    let user_present = authenticator_data
+        .get(FLAGS_OFFSET)
+        .is_some_and(|flags| flags & FLAG_USER_PRESENT != 0);
+    if !user_present || find(client_data_json, GET_TYPE).is_none() {
+        return Ok(false);
+    }
+    let sighash = generate_sighash_all_hash()?;
+    if !challenge_matches(client_data_json, &sighash) {
+        return Ok(false);
+    }
+
+    let seal = load_group_lock()?;
+    let signature = match Signature::from_slice(&seal) {
+        Ok(s) => s,
+        Err(_) => return Ok(false),
+    };
+    let key = {
+        let mut sec1 = [4u8; 65];
+        sec1[1..].copy_from_slice(pubkey);
+        match VerifyingKey::from_sec1_bytes(&sec1) {
+            Ok(k) => k,
+            Err(_) => return Ok(false),
+        }
+    };
+
+    // Signed message is authenticator data followed by SHA-256 hash of
+    // client data JSON.
+    let mut message = Vec::with_capacity(authenticator_data.len() + 32);
+    message.extend_from_slice(authenticator_data);
+    message.extend_from_slice(&Sha256::digest(client_data_json));
+
+    Ok(key.verify(&message, &signature).is_ok())
+}
+
+fn challenge_matches(client_data_json: &[u8], sighash: &[u8; 32]) -> bool {
+    let start = match find(client_data_json, CHALLENGE_KEY) {
+        Some(p) => p + CHALLENGE_KEY.len(),
+        None => return false,
+    };
+    let expected = base64url_encode(sighash);
+    let end = start + expected.len();
+    client_data_json
+        .get(start..end)
+        .is_some_and(|challenge| ct_eq(challenge, &expected))
+        && client_data_json.get(end) == Some(&b'"')
+}
+
+// Position of the first occurrence of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn base64url_encode(data: &[u8; 32]) -> [u8; 43] {
+    let mut result = [0u8; 43];
+    let mut bits = 0u32;
+    let mut available = 0;
+    let mut i = 0;
+    for b in data {
+        bits = (bits << 8) | (*b as u32);
+        available += 8;
+        while available >= 6 {
+            available -= 6;
+            result[i] = BASE64URL_ALPHABET[((bits >> available) & 0x3F) as usize];
+            i += 1;
+        }
+    }
+    // 256 bits leave 4 trailing bits, padded with zeros
+    result[i] = BASE64URL_ALPHABET[((bits << (6 - available)) & 0x3F) as usize];
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builder::build_sighash_witness, MockTransaction};
+    use alloc::{format, vec};
+    use molecule::prelude::Entity;
+    use p256::ecdsa::{signature::Signer, SigningKey};
+
+    // Authenticator data: RP ID hash, flags, then signature counter
+    fn authenticator_data(flags: u8) -> Vec<u8> {
+        let mut data = vec![0x49; 32];
+        data.push(flags);
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data
+    }
+
+    fn client_data_json(ty: &str, challenge: &[u8]) -> Vec<u8> {
+        let challenge = core::str::from_utf8(challenge).unwrap();
+        format!(
+            r#"{{"type":"{}","challenge":"{}","origin":"https://example.com"}}"#,
+            ty, challenge
+        )
+        .into_bytes()
+    }
+
+    fn install(seal: &[u8]) {
+        let witness = build_sighash_witness(seal);
+        MockTransaction {
+            tx_hash: [4u8; 32],
+            input_count: 1,
+            group_inputs: vec![0],
+            witnesses: vec![witness.as_slice().to_vec()],
+            ..Default::default()
+        }
+        .install();
+    }
+
+    // Builds an assertion over sighash-all message hash of the transaction
+    // built by `install`, which does not depend on the seal. Returns the
+    // public key, client data JSON and the signature.
+    fn assertion(authenticator_data: &[u8], ty: &str) -> ([u8; 64], Vec<u8>, Vec<u8>) {
+        install(&[]);
+        let challenge = base64url_encode(&generate_sighash_all_hash().unwrap());
+        let client_data_json = client_data_json(ty, &challenge);
+
+        let key = SigningKey::from_slice(&[1u8; 32]).unwrap();
+        let mut message = authenticator_data.to_vec();
+        message.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature: Signature = key.sign(&message);
+        let pubkey = key.verifying_key().to_encoded_point(false).as_bytes()[1..]
+            .try_into()
+            .unwrap();
+        (pubkey, client_data_json, signature.to_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_verify_webauthn() {
+        let authenticator_data = authenticator_data(0x05);
+        let (pubkey, client_data_json, seal) = assertion(&authenticator_data, "webauthn.get");
+        // P-256 signing is deterministic(RFC 6979), pinning the assertion
+        let expected: [u8; 64] = [
+            0xa3, 0x40, 0x33, 0xe7, 0x43, 0xa7, 0x19, 0x71, 0x7d, 0x49, 0xf8, 0xaf, 0x2c, 0xf4,
+            0xf8, 0x7d, 0x23, 0x22, 0x7a, 0x9b, 0xc3, 0xec, 0xd1, 0x2e, 0x6c, 0x78, 0xef, 0x0b,
+            0x99, 0xd7, 0x86, 0x9a, 0xa4, 0xf8, 0xfa, 0xd4, 0x79, 0xbc, 0x4a, 0xa0, 0x56, 0x12,
+            0x2d, 0xd3, 0x28, 0xa2, 0x6f, 0x4f, 0x09, 0x44, 0xee, 0x47, 0xb7, 0x12, 0x40, 0x59,
+            0xbc, 0x9a, 0x96, 0x4f, 0x7e, 0x37, 0xa4, 0x19,
+        ];
+        assert_eq!(seal, expected);
+        install(&seal);
+        assert_eq!(
+            verify_webauthn(&pubkey, &authenticator_data, &client_data_json),
+            Ok(true)
+        );
+
+        let mut other = client_data_json.clone();
+        let p = find(&other, CHALLENGE_KEY).unwrap() + CHALLENGE_KEY.len();
+        other[p] ^= 1;
+        assert_eq!(
+            verify_webauthn(&pubkey, &authenticator_data, &other),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn test_verify_webauthn_create_type() {
+        let authenticator_data = authenticator_data(0x05);
+        let (pubkey, client_data_json, seal) = assertion(&authenticator_data, "webauthn.create");
+        install(&seal);
+        assert_eq!(
+            verify_webauthn(&pubkey, &authenticator_data, &client_data_json),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn test_verify_webauthn_user_not_present() {
+        let authenticator_data = authenticator_data(0x04);
+        let (pubkey, client_data_json, seal) = assertion(&authenticator_data, "webauthn.get");
+        install(&seal);
+        assert_eq!(
+            verify_webauthn(&pubkey, &authenticator_data, &client_data_json),
+            Ok(false)
+        );
+        assert_eq!(
+            verify_webauthn(&pubkey, &authenticator_data[..32], &client_data_json),
+            Ok(false)
+        );
+    }
+}
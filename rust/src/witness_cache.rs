@@ -0,0 +1,128 @@
+//! Opt-in cache of loaded witnesses. Scripts running several passes over
+//! witnesses, e.g. `fetch_sighash_with_action_cached` followed by
+//! `count_sighash_with_action_cached`, pay the syscall for each witness only
+//! once, later passes read from memory instead.
+//!
+//! The tradeoff is memory: every witness visited stays in heap until the
+//! cache is dropped, so a transaction with large witnesses can exhaust the
+//! limited memory of CKB-VM. Memory-constrained scripts, or scripts doing a
+//! single pass, should stick to the cache-free functions, which only hold
+//! one witness at a time.
+
+use crate::syscalls;
+use alloc::{borrow::Cow, collections::BTreeMap, vec::Vec};
+use ckb_std::{ckb_constants::Source, error::SysError};
+
+/// Witnesses loaded so far, keyed by `(Source, index)`. Failed loads are
+/// cached as well, so the end of witnesses is only probed once.
+///
+/// ```
+/// # #[cfg(feature = "mock-syscalls")] {
+/// use ckb_typed_message_signing::{
+///     count_sighash_with_action_cached, fetch_sighash_with_action_cached,
+///     schemas::{basic::SighashWithAction, top_level::ExtendedWitness},
+///     witness_cache::WitnessCache,
+///     MockTransaction,
+/// };
+/// use molecule::prelude::{Builder, Entity};
+///
+/// let witness = ExtendedWitness::new_builder()
+///     .set(SighashWithAction::default())
+///     .build();
+/// MockTransaction {
+///     input_count: 1,
+///     group_inputs: vec![0],
+///     witnesses: vec![vec![], witness.as_slice().to_vec()],
+///     ..Default::default()
+/// }
+/// .install();
+///
+/// let mut cache = WitnessCache::new();
+/// fetch_sighash_with_action_cached(&mut cache).unwrap();
+/// // 2 witnesses plus the out of bound probe
+/// assert_eq!(cache.len(), 3);
+/// assert_eq!(count_sighash_with_action_cached(&mut cache).unwrap(), 1);
+/// assert_eq!(cache.len(), 3);
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct WitnessCache {
+    witnesses: BTreeMap<(u64, usize), Result<Vec<u8>, SysError>>,
+}
+
+impl WitnessCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads witness `index` in `source`, issuing a syscall only when the
+    /// witness has not been loaded before.
+    pub fn load(&mut self, index: usize, source: Source) -> Result<&[u8], SysError> {
+        self.witnesses
+            .entry((source as u64, index))
+            .or_insert_with(|| syscalls::load_witness(index, source))
+            .as_deref()
+            .map_err(|e| *e)
+    }
+
+    /// Number of witnesses, including failed loads, kept in the cache
+    pub fn len(&self) -> usize {
+        self.witnesses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.witnesses.is_empty()
+    }
+
+    /// Drops all cached witnesses, releasing their memory
+    pub fn clear(&mut self) {
+        self.witnesses.clear();
+    }
+}
+
+/// Where scanning functions, such as `ExtendedWitnessIter`, load witnesses
+/// from: directly via syscalls, or through a `WitnessCache`.
+pub trait WitnessLoader {
+    fn load_witness(&mut self, index: usize, source: Source) -> Result<Cow<'_, [u8]>, SysError>;
+
+    /// Length of a witness, used to probe whether a witness exists without
+    /// loading it
+    fn load_witness_len(&mut self, index: usize, source: Source) -> Result<usize, SysError>;
+}
+
+/// Loads each witness via syscall, keeping nothing in memory
+pub struct DirectLoader;
+
+impl WitnessLoader for DirectLoader {
+    fn load_witness(&mut self, index: usize, source: Source) -> Result<Cow<'_, [u8]>, SysError> {
+        syscalls::load_witness(index, source).map(Cow::Owned)
+    }
+
+    fn load_witness_len(&mut self, index: usize, source: Source) -> Result<usize, SysError> {
+        syscalls::load_witness_len(index, source)
+    }
+}
+
+impl<L: WitnessLoader> WitnessLoader for &mut L {
+    fn load_witness(&mut self, index: usize, source: Source) -> Result<Cow<'_, [u8]>, SysError> {
+        (**self).load_witness(index, source)
+    }
+
+    fn load_witness_len(&mut self, index: usize, source: Source) -> Result<usize, SysError> {
+        (**self).load_witness_len(index, source)
+    }
+}
+
+impl WitnessLoader for WitnessCache {
+    fn load_witness(&mut self, index: usize, source: Source) -> Result<Cow<'_, [u8]>, SysError> {
+        self.load(index, source).map(Cow::Borrowed)
+    }
+
+    // Probes are not cached, a cached witness answers without a syscall
+    fn load_witness_len(&mut self, index: usize, source: Source) -> Result<usize, SysError> {
+        match self.witnesses.get(&(source as u64, index)) {
+            Some(witness) => witness.as_ref().map(|w| w.len()).map_err(|e| *e),
+            None => syscalls::load_witness_len(index, source),
+        }
+    }
+}
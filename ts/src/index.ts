@@ -13,6 +13,7 @@ export type RefCell = {
     source: BIish;
     index: number;
     offset: number;
+    keccak: boolean;
   };
 };
 
@@ -20,6 +21,24 @@ export type RefTransaction = {
   type: "RefTransaction";
   value: {
     offset: number;
+    length: number;
+    keccak: boolean;
+  };
+};
+
+export type RefScript = {
+  type: "RefScript";
+  value: {};
+};
+
+// Bytes value kept in cell data, usable in place of inline bytes
+export type RefBytes = {
+  type: "RefBytes";
+  value: {
+    source: BIish;
+    index: number;
+    offset: number;
+    length: number;
   };
 };
 
@@ -28,7 +47,7 @@ export type Byte32 = {
   value: Uint8Array;
 };
 
-export type Hash = Byte32 | RefCell | RefTransaction;
+export type Hash = Byte32 | RefCell | RefTransaction | RefScript;
 
 export interface HashGenerator {
   domain_separator: (data: TypedData) => Hash;
@@ -84,26 +103,11 @@ export function parseTypedMessage(
     );
   }
   const message = unpacked.value;
-  const expectedDomainSeparator = hasher.domain_separator(data);
-  if (
-    expectedDomainSeparator.type === "Byte32" &&
-    message.domain_separator.type === "Byte32"
-  ) {
-    if (
-      !isEqual(
-        expectedDomainSeparator.value,
-        bytes.bytify(message.domain_separator.value),
-      )
-    ) {
-      throw new Error(
-        `Expected domain separator: ${
-          expectedDomainSeparator.value
-        }, actual domain separator: ${bytes.bytify(
-          message.domain_separator.value,
-        )}`,
-      );
-    }
-  }
+  checkHash(
+    hasher.domain_separator(data),
+    message.domain_separator,
+    "domain separator",
+  );
 
   const parsedMessage = parseStruct(
     data,
@@ -213,6 +217,9 @@ function buildValue(
       break;
     case "bytes":
       {
+        if (value?.type === "RefBytes") {
+          return value;
+        }
         return {
           type: "Bytes",
           value: bytes.bytify(value),
@@ -355,6 +362,11 @@ function parseValue(
       break;
     case "bytes":
       {
+        // Referenced bytes live in cell data, which is not available here,
+        // the reference itself is returned instead.
+        if (packed.type === "RefBytes") {
+          return packed;
+        }
         if (packed.type !== "Bytes") {
           throw new Error(`Expected Bytes union type but found ${packed.type}`);
         }
@@ -486,21 +498,7 @@ function parseStruct(
   if (!(type in data.types)) {
     throw new Error(`${type} is not found in defined types!`);
   }
-  const expectedTypeHash = hasher.type_hash(data, type);
-  if (
-    expectedTypeHash.type === "Byte32" &&
-    packed.type_hash.type === "Byte32"
-  ) {
-    if (
-      !isEqual(expectedTypeHash.value, bytes.bytify(packed.type_hash.value))
-    ) {
-      throw new Error(
-        `Expected type hash: ${
-          expectedTypeHash.value
-        }, actual type hash: ${bytes.bytify(packed.type_hash.value)}`,
-      );
-    }
-  }
+  checkHash(hasher.type_hash(data, type), packed.type_hash, "type hash");
 
   const typeDefinition = data.types[type];
   if (typeDefinition.length !== packed.values.length) {
@@ -521,3 +519,24 @@ function parseStruct(
 
   return values;
 }
+
+// Referenced hashes other than RefScript cannot be resolved off-chain, they
+// are only compared against literal hashes of the same kind. RefScript binds
+// a message to the executing script, so it must be decoded from exactly
+// where the hash generator expects one.
+function checkHash(expected: Hash, actual: Hash, name: string) {
+  if (expected.type === "Byte32" && actual.type === "Byte32") {
+    if (!isEqual(expected.value, bytes.bytify(actual.value))) {
+      throw new Error(
+        `Expected ${name}: ${expected.value}, actual ${name}: ${bytes.bytify(
+          actual.value,
+        )}`,
+      );
+    }
+  }
+  if ((expected.type === "RefScript") !== (actual.type === "RefScript")) {
+    throw new Error(
+      `Expected ${name} of ${expected.type} type, but found ${actual.type}`,
+    );
+  }
+}